@@ -0,0 +1,24 @@
+#!/bin/sh
+# Renders the start screen on the software GL path and compares it with
+# tests/golden/start.png. Needs Xvfb and Mesa (llvmpipe). Pass --update to
+# write a new golden image instead, after checking the change on screen.
+#
+#   ci/golden.sh [--update]
+set -e
+cd "$(dirname "$0")/.."
+
+# No saved settings, scores or wallet, and English text.
+home=$(mktemp -d)
+trap 'rm -rf "$home"' EXIT
+export HOME="$home" XDG_DATA_HOME="$home/data" XDG_CONFIG_HOME="$home/config" XDG_PICTURES_DIR=
+export LC_ALL=C LANG=C LIBGL_ALWAYS_SOFTWARE=1
+
+cargo build
+run="xvfb-run -a -s -screen\ 0\ 1024x768x24 target/debug/rusty_bird --seed 1 --screenshot-at 120"
+if [ "$1" = --update ]; then
+    shot=$($run | sed -n 's/^saved screenshot //p')
+    cp "$shot" tests/golden/start.png
+    echo "updated tests/golden/start.png"
+else
+    $run --golden tests/golden/start.png
+fi
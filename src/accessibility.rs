@@ -0,0 +1,217 @@
+use macroquad::prelude::*;
+
+use crate::locale::Strings;
+use crate::settings::Settings;
+use crate::text::{Align, Fonts, TextStyle};
+
+// Colors for UI text and for the flat shapes drawn in high-contrast mode.
+// The colorblind variant uses the Okabe-Ito palette so on/off and
+// highlight colors never rely on telling red from green.
+pub struct Palette {
+    pub text: Color,
+    pub outline: Color,
+    pub highlight: Color,
+    pub on: Color,
+    pub off: Color,
+
+    pub sky: Color,
+    pub pipe: Color,
+    pub pipe_outline: Color,
+    pub bird: Color,
+    pub bird_outline: Color,
+    pub ground: Color,
+}
+
+impl Palette {
+    pub fn new(settings: &Settings) -> Palette {
+        let (highlight, on, off) = if settings.colorblind {
+            (Color::from_hex(0xf0e442), Color::from_hex(0x56b4e9), Color::from_hex(0xe69f00))
+        } else {
+            (GOLD, GREEN, RED)
+        };
+        Palette {
+            text: WHITE,
+            outline: BLACK,
+            highlight,
+            on,
+            off,
+
+            sky: BLACK,
+            pipe: WHITE,
+            pipe_outline: Color::from_hex(0x56b4e9),
+            bird: Color::from_hex(0xf0e442),
+            bird_outline: BLACK,
+            ground: Color::from_hex(0x56b4e9),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Item {
+    GameSpeed,
+    GapAssist,
+    HoldToHover,
+    HighContrast,
+    Colorblind,
+    ScreenShake,
+    HitStop,
+    Flash,
+    DeathZoom,
+}
+
+const ITEMS: [Item; 9] = [
+    Item::GameSpeed,
+    Item::GapAssist,
+    Item::HoldToHover,
+    Item::HighContrast,
+    Item::Colorblind,
+    Item::ScreenShake,
+    Item::HitStop,
+    Item::Flash,
+    Item::DeathZoom,
+];
+
+impl Item {
+    fn key(&self) -> &'static str {
+        match self {
+            Item::GameSpeed => "menu.game_speed",
+            Item::GapAssist => "menu.gap_assist",
+            Item::HoldToHover => "menu.hold_to_hover",
+            Item::HighContrast => "menu.high_contrast",
+            Item::Colorblind => "menu.colorblind",
+            Item::ScreenShake => "menu.screen_shake",
+            Item::HitStop => "menu.hit_stop",
+            Item::Flash => "menu.flash",
+            Item::DeathZoom => "menu.death_zoom",
+        }
+    }
+
+    // Left/right step. Percentages move in 10% steps within their range,
+    // on/off items flip either way.
+    fn change(&self, settings: &mut Settings, step: i32) {
+        let tenth = step as f32 * 0.1;
+        match self {
+            Item::GameSpeed => settings.game_speed = round_tenth(settings.game_speed + tenth).clamp(0.5, 1.0),
+            Item::GapAssist => settings.gap_assist = round_tenth(settings.gap_assist + tenth).clamp(1.0, 1.5),
+            Item::HoldToHover => settings.hold_to_hover ^= true,
+            Item::HighContrast => settings.high_contrast ^= true,
+            Item::Colorblind => settings.colorblind ^= true,
+            Item::ScreenShake => settings.screen_shake ^= true,
+            Item::HitStop => settings.hit_stop ^= true,
+            Item::Flash => settings.flash ^= true,
+            Item::DeathZoom => settings.death_zoom ^= true,
+        }
+    }
+
+    fn value(&self, settings: &Settings) -> Value {
+        match self {
+            Item::GameSpeed => Value::Percent(settings.game_speed),
+            Item::GapAssist => Value::Percent(settings.gap_assist),
+            Item::HoldToHover => Value::Flag(settings.hold_to_hover),
+            Item::HighContrast => Value::Flag(settings.high_contrast),
+            Item::Colorblind => Value::Flag(settings.colorblind),
+            Item::ScreenShake => Value::Flag(settings.screen_shake),
+            Item::HitStop => Value::Flag(settings.hit_stop),
+            Item::Flash => Value::Flag(settings.flash),
+            Item::DeathZoom => Value::Flag(settings.death_zoom),
+        }
+    }
+}
+
+enum Value {
+    Percent(f32),
+    Flag(bool),
+}
+
+fn round_tenth(value: f32) -> f32 {
+    (value * 10.0).round() / 10.0
+}
+
+// Keyboard-driven settings screen opened with Tab from the start screen.
+// Changes apply immediately and are saved to settings.txt.
+pub struct AccessibilityMenu {
+    pub open: bool,
+    selected: usize,
+}
+
+impl AccessibilityMenu {
+    pub fn new() -> AccessibilityMenu {
+        AccessibilityMenu { open: false, selected: 0 }
+    }
+
+    pub fn update(&mut self, settings: &mut Settings) {
+        if is_key_pressed(KeyCode::Tab) || (self.open && is_key_pressed(KeyCode::Escape)) {
+            self.open = !self.open;
+            return;
+        }
+        if !self.open {
+            return;
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.selected = (self.selected + 1) % ITEMS.len();
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.selected = (self.selected + ITEMS.len() - 1) % ITEMS.len();
+        }
+        let step = if is_key_pressed(KeyCode::Right) || is_key_pressed(KeyCode::Enter) {
+            1
+        } else if is_key_pressed(KeyCode::Left) {
+            -1
+        } else {
+            0
+        };
+        if step != 0 {
+            ITEMS[self.selected].change(settings, step);
+            settings.save();
+        }
+    }
+
+    pub fn draw(&self, settings: &Settings, fonts: &Fonts, strings: &Strings, palette: &Palette) {
+        draw_rectangle(0.0, 0.0, screen_width(), screen_height(), Color::new(0.0, 0.0, 0.0, 0.75));
+        let text = TextStyle {
+            color: palette.text,
+            anchor: vec2(0.5, 0.0),
+            outline: Some((2.0, palette.outline)),
+            ..Default::default()
+        };
+
+        fonts.draw(strings.get("menu.title"), &TextStyle {
+            size: 40.0,
+            align: Align::Center,
+            offset: vec2(0.0, 60.0),
+            ..text
+        });
+
+        for (n, item) in ITEMS.iter().enumerate() {
+            let y = 130.0 + n as f32 * 40.0;
+            let color = if n == self.selected { palette.highlight } else { palette.text };
+            fonts.draw(strings.get(item.key()), &TextStyle {
+                size: 26.0,
+                color,
+                offset: vec2(-300.0, y),
+                max_width: Some(420.0),
+                ..text
+            });
+            let (value, value_color) = match item.value(settings) {
+                Value::Percent(value) => (format!("{}%", (value * 100.0).round()), color),
+                Value::Flag(true) => (strings.get("menu.on").to_owned(), palette.on),
+                Value::Flag(false) => (strings.get("menu.off").to_owned(), palette.off),
+            };
+            fonts.draw(&value, &TextStyle {
+                size: 26.0,
+                color: value_color,
+                align: Align::Right,
+                offset: vec2(300.0, y),
+                ..text
+            });
+        }
+
+        fonts.draw(strings.get("menu.hint"), &TextStyle {
+            size: 18.0,
+            align: Align::Center,
+            offset: vec2(0.0, 520.0),
+            max_width: Some(760.0),
+            ..text
+        });
+    }
+}
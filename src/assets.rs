@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::fs;
+use std::time::SystemTime;
+
+use macroquad::prelude::*;
+use macroquad::miniquad::TextureWrap;
+
+use crate::cli::Args;
+
+pub const ASSET_DIR: &str = "assets";
+
+pub struct Assets {
+    pub flappy: Texture2D,
+    pub flappy_jump: Texture2D,
+    pub pipe_upper: Texture2D,
+    pub pipe_lower: Texture2D,
+    pub pipe_body: Texture2D,
+    pub gobg: Texture2D,
+    pub ground: Texture2D,
+}
+
+impl Assets {
+    pub async fn load() -> Assets {
+        Assets {
+            flappy: texture("flappy.png").await.unwrap(),
+            flappy_jump: texture("flappy_jump.png").await.unwrap(),
+            pipe_upper: texture("pipe_upper.png").await.unwrap(),
+            pipe_lower: texture("pipe_lower.png").await.unwrap(),
+            pipe_body: repeating(texture("pipe_body.png").await.unwrap()),
+            gobg: texture("gobg.png").await.unwrap(),
+            ground: texture("ground.png").await.unwrap(),
+        }
+    }
+
+    // Swaps a single asset in place. Files that fail to load (e.g. an editor
+    // still writing them) keep the old handle and get picked up on the next change.
+    pub async fn reload(&mut self, name: &str) {
+        let slot = match name {
+            "flappy.png" => &mut self.flappy,
+            "flappy_jump.png" => &mut self.flappy_jump,
+            "pipe_upper.png" => &mut self.pipe_upper,
+            "pipe_lower.png" => &mut self.pipe_lower,
+            "pipe_body.png" => &mut self.pipe_body,
+            "gobg.png" => &mut self.gobg,
+            "ground.png" => &mut self.ground,
+            _ => return,
+        };
+        match texture(name).await {
+            Ok(new) => {
+                *slot = if name == "pipe_body.png" { repeating(new) } else { new };
+                println!("reloaded {}", name);
+            }
+            Err(err) => println!("failed to reload {}: {}", name, err),
+        }
+    }
+}
+
+pub async fn texture(name: &str) -> Result<Texture2D, macroquad::Error> {
+    load_texture(&format!("{}/{}", ASSET_DIR, name)).await
+}
+
+// Lets a single quad tile the texture by drawing it with a source rect
+// larger than the image.
+fn repeating(texture: Texture2D) -> Texture2D {
+    unsafe {
+        get_internal_gl().quad_context.texture_set_wrap(
+            texture.raw_miniquad_id(),
+            TextureWrap::Repeat,
+            TextureWrap::Repeat,
+        );
+    }
+    texture
+}
+
+// Polls modification times in the assets directory and its subfolders. Only
+// used in dev builds or with --dev, so artists can tweak files while the
+// game is running.
+pub struct AssetWatcher {
+    stamps: HashMap<String, SystemTime>,
+    last_poll: f64,
+}
+
+impl AssetWatcher {
+    const POLL_INTERVAL: f64 = 0.5;
+
+    pub fn enabled(args: &Args) -> bool {
+        cfg!(debug_assertions) || args.dev
+    }
+
+    pub fn new() -> AssetWatcher {
+        AssetWatcher {
+            stamps: scan(),
+            last_poll: get_time(),
+        }
+    }
+
+    pub fn changed(&mut self) -> Vec<String> {
+        if get_time() - self.last_poll < Self::POLL_INTERVAL {
+            return vec![];
+        }
+        self.last_poll = get_time();
+
+        let stamps = scan();
+        let changed = stamps
+            .iter()
+            .filter(|(name, stamp)| self.stamps.get(*name) != Some(*stamp))
+            .map(|(name, _)| name.clone())
+            .collect();
+        self.stamps = stamps;
+        changed
+    }
+}
+
+// Modification times of everything under assets, keyed by the path
+// relative to it (`ground.png`, `skins/cardinal/flappy.png`).
+fn scan() -> HashMap<String, SystemTime> {
+    let mut stamps = HashMap::new();
+    scan_dir(ASSET_DIR, "", &mut stamps);
+    stamps
+}
+
+fn scan_dir(dir: &str, prefix: &str, stamps: &mut HashMap<String, SystemTime>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if meta.is_dir() {
+            scan_dir(&format!("{}/{}", dir, entry.file_name().to_string_lossy()), &format!("{}/", name), stamps);
+        } else if let Ok(modified) = meta.modified() {
+            stamps.insert(name, modified);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use macroquad::prelude::*;
+use macroquad::audio::{load_sound, play_sound, PlaySoundParams, Sound};
+
+use crate::assets::ASSET_DIR;
+use crate::music::{Music, MusicState};
+use crate::settings::Settings;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Sfx {
+    Flap,
+    Hit,
+    Point,
+    Coin,
+}
+
+impl Sfx {
+    // Mix level of each effect before the SFX bus.
+    fn gain(&self) -> f32 {
+        match self {
+            Sfx::Flap => 1.0,
+            Sfx::Hit => 1.0,
+            Sfx::Point => 0.1,
+            Sfx::Coin => 0.3,
+        }
+    }
+}
+
+const MUSIC_GAIN: f32 = 0.2;
+
+// Music level while ducked, relative to normal, and how fast (per second)
+// it fades to and from that level.
+const DUCK_LEVEL: f32 = 0.35;
+const DUCK_SPEED: f32 = 2.0;
+
+// Scoring several pipes in quick succession would otherwise stack up
+// copies of point.ogg. At most this many are started per window.
+const POINT_VOICES: usize = 3;
+const POINT_WINDOW: f64 = 0.4;
+
+// Owns every sound and plays them through master, music and SFX buses
+// whose levels come from settings.txt.
+pub struct AudioManager {
+    flap: Sound,
+    hit: Sound,
+    point: Sound,
+    coin: Sound,
+    music: Music,
+
+    master: f32,
+    music_bus: f32,
+    sfx_bus: f32,
+    muted: bool,
+
+    ducked: bool,
+    duck: f32,
+    // Start times of the point sounds still within POINT_WINDOW.
+    point_voices: Vec<f64>,
+}
+
+impl AudioManager {
+    pub async fn load(settings: &Settings) -> AudioManager {
+        let mut audio = AudioManager {
+            flap: sound("flap.ogg").await.unwrap(),
+            hit: sound("hit.ogg").await.unwrap(),
+            point: sound("point.ogg").await.unwrap(),
+            coin: sound("coin.wav").await.unwrap(),
+            music: Music::load().await,
+
+            master: 1.0,
+            music_bus: 1.0,
+            sfx_bus: 1.0,
+            muted: false,
+
+            ducked: false,
+            duck: 1.0,
+            point_voices: vec![],
+        };
+        audio.apply(settings);
+        audio
+    }
+
+    // Picks up changed volume settings.
+    pub fn apply(&mut self, settings: &Settings) {
+        self.master = settings.master_volume;
+        self.music_bus = settings.music_volume;
+        self.sfx_bus = settings.sfx_volume;
+        self.muted = settings.mute;
+    }
+
+    pub fn set_music(&mut self, state: MusicState, score: i64) {
+        self.music.set_state(state, score);
+    }
+
+    pub fn play(&mut self, sfx: Sfx) {
+        self.play_as(sfx, None);
+    }
+
+    // Plays `sound` instead of the effect's own file, at the effect's level.
+    pub fn play_as(&mut self, sfx: Sfx, sound: Option<&Sound>) {
+        if sfx == Sfx::Point {
+            let now = get_time();
+            self.point_voices.retain(|&start| now - start < POINT_WINDOW);
+            if self.point_voices.len() >= POINT_VOICES {
+                return;
+            }
+            self.point_voices.push(now);
+        }
+        let sound = sound.unwrap_or(match sfx {
+            Sfx::Flap => &self.flap,
+            Sfx::Hit => &self.hit,
+            Sfx::Point => &self.point,
+            Sfx::Coin => &self.coin,
+        });
+        play_sound(sound, PlaySoundParams { looped: false, volume: self.sfx_volume() * sfx.gain() });
+    }
+
+    // Lowers the music until called with false again.
+    pub fn set_ducked(&mut self, ducked: bool) {
+        self.ducked = ducked;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let target = if self.ducked { DUCK_LEVEL } else { 1.0 };
+        let step = DUCK_SPEED * dt;
+        self.duck = if self.duck < target { (self.duck + step).min(target) } else { (self.duck - step).max(target) };
+        self.music.update(dt, self.music_volume());
+    }
+
+    pub async fn reload(&mut self, name: &str) {
+        if self.music.uses(name) {
+            self.music.stop();
+            let (state, score) = (self.music.state, self.music.score);
+            self.music = Music::load().await;
+            self.music.set_state(state, score);
+            println!("reloaded {}", name);
+        }
+        let slot = match name {
+            "flap.ogg" => &mut self.flap,
+            "hit.ogg" => &mut self.hit,
+            "point.ogg" => &mut self.point,
+            "coin.wav" => &mut self.coin,
+            _ => return,
+        };
+        match sound(name).await {
+            Ok(new) => {
+                *slot = new;
+                println!("reloaded {}", name);
+            }
+            Err(err) => println!("failed to reload {}: {}", name, err),
+        }
+    }
+
+    fn bus(&self, level: f32) -> f32 {
+        if self.muted { 0.0 } else { self.master * level }
+    }
+
+    fn music_volume(&self) -> f32 {
+        self.bus(self.music_bus) * MUSIC_GAIN * self.duck
+    }
+
+    fn sfx_volume(&self) -> f32 {
+        self.bus(self.sfx_bus)
+    }
+}
+
+async fn sound(name: &str) -> Result<Sound, macroquad::Error> {
+    load_sound(&format!("{}/{}", ASSET_DIR, name)).await
+}
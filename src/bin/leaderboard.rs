@@ -0,0 +1,330 @@
+// Leaderboard for the daily challenge: one board per day's seed, and an
+// all-time board over every day. Only runs on today's course are taken, and
+// a score is only kept once the game has flown its replay again with no
+// window (`rusty_bird --verify`) and got the same result. Scores live in
+// SQLite.
+//
+//   cargo build && cargo run --features leaderboard --bin leaderboard [address] [database]
+//                                   (default 127.0.0.1:8787 leaderboard.db)
+//   cargo run -- --leaderboard 127.0.0.1:8787 --name alice
+//
+// The game binary is looked for next to this one; set RUSTY_BIRD to use
+// another. Plain HTTP/1.0, text in and out:
+//
+//   POST /submit          body `name seed score flap...`, 200, 400 if the
+//                         name isn't 1-16 of A-Z a-z 0-9 _ -, 403 if the
+//                         seed isn't today's, or 422 if the replay doesn't
+//                         make that score
+//   GET /top?seed=<n>     best score per name on one day's seed, `name score`
+//                         lines
+//   GET /top              the same over every day
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection};
+
+const DEFAULT_PORT: u16 = 8787;
+const DATABASE: &str = "leaderboard.db";
+// Entries in a top list.
+const TOP: i64 = 10;
+// Longest name. Names are one line's first word in the top lists, so only
+// letters, digits, `_` and `-` go in.
+const MAX_NAME: usize = 16;
+// A long run is a few thousand flaps; anything much bigger is junk.
+const MAX_BODY: usize = 256 * 1024;
+// Checking a replay takes milliseconds; a game that hangs is killed.
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Functions the board is opened with, which tests swap out.
+type Verify = Box<dyn Fn(&str) -> bool + Send + Sync>;
+type Today = Box<dyn Fn() -> i64 + Send + Sync>;
+
+// Shared by the connection threads. Only the database is locked, so a slow
+// replay check doesn't hold up the others.
+struct Board {
+    db: Mutex<Connection>,
+    // Checks `seed score flap...`.
+    verify: Verify,
+    // Today's daily seed.
+    today: Today,
+}
+
+impl Board {
+    fn open(db: Connection, verify: Verify, today: Today) -> rusqlite::Result<Board> {
+        db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS scores (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                seed INTEGER NOT NULL,
+                score INTEGER NOT NULL,
+                replay TEXT NOT NULL,
+                submitted INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            );
+            CREATE INDEX IF NOT EXISTS scores_by_seed ON scores (seed, score);",
+        )?;
+        Ok(Board { db: Mutex::new(db), verify, today })
+    }
+
+    // Answers one request with a status code and body.
+    fn respond(&self, method: &str, path: &str, body: &str) -> (u16, String) {
+        let result = match (method, path.split_once('?')) {
+            ("POST", None) if path == "/submit" => return self.submit(body),
+            ("GET", None) if path == "/top" => self.top(None),
+            ("GET", Some(("/top", query))) => match query.strip_prefix("seed=").and_then(|seed| seed.parse().ok()) {
+                Some(seed) => self.top(Some(seed)),
+                None => return (400, "bad seed\n".to_owned()),
+            },
+            _ => return (404, "not found\n".to_owned()),
+        };
+        match result {
+            Ok(scores) => (200, scores.iter().map(|(name, score)| format!("{} {}\n", name, score)).collect()),
+            Err(err) => {
+                println!("database error: {}", err);
+                (500, "database error\n".to_owned())
+            }
+        }
+    }
+
+    fn submit(&self, body: &str) -> (u16, String) {
+        let body = body.trim();
+        let fields: Vec<&str> = body.splitn(4, ' ').collect();
+        let (name, seed, score) = match fields.as_slice() {
+            [name, seed, score, _] => match (seed.parse::<i64>(), score.parse::<i64>()) {
+                (Ok(seed), Ok(score)) => (*name, seed, score),
+                _ => return (400, "bad submission\n".to_owned()),
+            },
+            _ => return (400, "bad submission\n".to_owned()),
+        };
+        if name.is_empty() || name.len() > MAX_NAME || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return (400, "bad name\n".to_owned());
+        }
+        let replay = &body[name.len() + 1..];
+        // Yesterday's board is closed, and other seeds have none.
+        if seed != (self.today)() {
+            println!("turned away {} for {} on seed {}", score, name, seed);
+            return (403, "not today's course\n".to_owned());
+        }
+        if !(self.verify)(replay) {
+            println!("rejected {} for {} on seed {}", score, name, seed);
+            return (422, "rejected\n".to_owned());
+        }
+        let inserted = self.db.lock().unwrap().execute("INSERT INTO scores (name, seed, score, replay) VALUES (?1, ?2, ?3, ?4)", params![name, seed, score, replay]);
+        match inserted {
+            Ok(_) => {
+                println!("{} scored {} on seed {}", name, score, seed);
+                (200, "ok\n".to_owned())
+            }
+            Err(err) => {
+                println!("database error: {}", err);
+                (500, "database error\n".to_owned())
+            }
+        }
+    }
+
+    // Best score per name on one seed, or on any, earliest first on a tie.
+    fn top(&self, seed: Option<i64>) -> rusqlite::Result<Vec<(String, i64)>> {
+        let db = self.db.lock().unwrap();
+        let mut query = db.prepare(
+            "SELECT name, MAX(score) AS best, MIN(submitted) AS first FROM scores
+             WHERE ?1 IS NULL OR seed = ?1
+             GROUP BY name ORDER BY best DESC, first, name LIMIT ?2",
+        )?;
+        let rows = query.query_map(params![seed, TOP], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let addr = args.next().unwrap_or_else(|| format!("127.0.0.1:{}", DEFAULT_PORT));
+    let database = args.next().unwrap_or_else(|| DATABASE.to_owned());
+    let game = std::env::var_os("RUSTY_BIRD").map(PathBuf::from).unwrap_or_else(|| {
+        let exe = std::env::current_exe().unwrap_or_default();
+        exe.with_file_name(format!("rusty_bird{}", std::env::consts::EXE_SUFFIX))
+    });
+    if !game.exists() {
+        println!("can't find the game at {} to check replays; build it or set RUSTY_BIRD", game.display());
+        std::process::exit(1);
+    }
+    let board = Connection::open(&database).and_then(|db| Board::open(db, Box::new(move |replay: &str| verify(&game, replay)), Box::new(today)));
+    let board = match board {
+        Ok(board) => board,
+        Err(err) => {
+            println!("failed to open {}: {}", database, err);
+            std::process::exit(1);
+        }
+    };
+    let listener = match TcpListener::bind(&addr) {
+        Ok(listener) => listener,
+        Err(err) => {
+            println!("failed to bind {}: {}", addr, err);
+            std::process::exit(1);
+        }
+    };
+    println!("leaderboard listening on {}, scores in {}", addr, database);
+    run(&listener, Arc::new(board));
+}
+
+// Day number since 1970 (UTC), the same as replay::daily_seed in the game.
+fn today() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| (time.as_secs() / 86400) as i64)
+}
+
+// Flies the replay again in the game; it exits 0 if the score holds up.
+// Gives up after VERIFY_TIMEOUT, so a stuck game can't hold up the server.
+fn verify(game: &Path, replay: &str) -> bool {
+    let child = Command::new(game).arg("--verify").stdin(Stdio::piped()).stdout(Stdio::null()).spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            println!("failed to run {}: {}", game.display(), err);
+            return false;
+        }
+    };
+    // From another thread, as a long replay can fill the pipe before the
+    // game reads it.
+    if let Some(mut stdin) = child.stdin.take() {
+        let replay = replay.to_owned();
+        std::thread::spawn(move || stdin.write_all(replay.as_bytes()));
+    }
+    let deadline = Instant::now() + VERIFY_TIMEOUT;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return status.success(),
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(5)),
+            Ok(None) => {
+                println!("{} --verify took too long", game.display());
+                let _ = child.kill();
+                let _ = child.wait();
+                return false;
+            }
+            Err(_) => return false,
+        }
+    }
+}
+
+// Each connection gets its own thread, as a slow client can take the read
+// timeout and its replay check up to VERIFY_TIMEOUT.
+fn run(listener: &TcpListener, board: Arc<Board>) {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let board = board.clone();
+        std::thread::spawn(move || handle(stream, &board));
+    }
+}
+
+fn handle(mut stream: TcpStream, board: &Board) {
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+    let (code, body) = match read_request(&stream) {
+        Some((method, path, body)) => board.respond(&method, &path, &body),
+        None => (400, "bad request\n".to_owned()),
+    };
+    let reason = match code {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        422 => "Unprocessable Entity",
+        _ => "Internal Server Error",
+    };
+    let response = format!("HTTP/1.0 {} {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}", code, reason, body.len(), body);
+    let _ = stream.write_all(response.as_bytes());
+}
+
+// Method, path and body.
+fn read_request(stream: &TcpStream) -> Option<(String, String, String)> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut words = line.split_whitespace();
+    let (method, path) = (words.next()?.to_owned(), words.next()?.to_owned());
+    let mut length = 0;
+    loop {
+        line.clear();
+        reader.read_line(&mut line).ok()?;
+        let header = line.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = header.split_once(':') {
+            if key.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().ok()?;
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return None;
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    Some((method, path, String::from_utf8(body).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    fn request(server: &str, method: &str, path: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(server).unwrap();
+        write!(stream, "{} {} HTTP/1.0\r\nContent-Length: {}\r\n\r\n{}", method, path, body.len(), body).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.split_whitespace().nth(1).unwrap().parse().unwrap(), body.to_owned())
+    }
+
+    // Stands in for the game: a replay is honest if it claims one point per
+    // flap after the first.
+    fn count_flaps(replay: &str) -> bool {
+        let fields: Vec<&str> = replay.split_whitespace().collect();
+        fields.len() >= 3 && fields[1].parse() == Ok(fields.len() - 3)
+    }
+
+    #[test]
+    fn keeps_verified_scores_on_todays_board_only() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = listener.local_addr().unwrap().to_string();
+        let day = Arc::new(AtomicI64::new(7));
+        let today = day.clone();
+        std::thread::spawn(move || {
+            let board = Board::open(Connection::open_in_memory().unwrap(), Box::new(count_flaps), Box::new(move || today.load(Ordering::SeqCst))).unwrap();
+            run(&listener, Arc::new(board));
+        });
+        // A client that never sends its request doesn't hold up the rest.
+        let _stalled = TcpStream::connect(&server).unwrap();
+        let start = Instant::now();
+
+        assert_eq!(request(&server, "POST", "/submit", "alice 7 2 0 10 20"), (200, "ok\n".to_owned()));
+        assert_eq!(request(&server, "POST", "/submit", "alice 7 1 0 10"), (200, "ok\n".to_owned()));
+        assert_eq!(request(&server, "POST", "/submit", "bob 7 3 0 10 20 30"), (200, "ok\n".to_owned()));
+        // Not today's course, however honest.
+        assert_eq!(request(&server, "POST", "/submit", "carol 8 4 0 1 2 3 4").0, 403);
+        // Claims more than the replay makes.
+        assert_eq!(request(&server, "POST", "/submit", "mallory 7 50 0 10").0, 422);
+        assert_eq!(request(&server, "POST", "/submit", "nonsense").0, 400);
+        // A newline in the name would add a fake row to the top lists.
+        assert_eq!(request(&server, "POST", "/submit", "eve\nmallory_99 7 1 0 10").0, 400);
+        assert_eq!(request(&server, "POST", "/submit", "a_name_far_too_long 7 1 0 10").0, 400);
+        assert_eq!(request(&server, "GET", "/top?seed=7", ""), (200, "bob 3\nalice 2\n".to_owned()));
+
+        // The next day opens a new board and closes the old one.
+        day.store(8, Ordering::SeqCst);
+        assert_eq!(request(&server, "POST", "/submit", "carol 8 4 0 1 2 3 4"), (200, "ok\n".to_owned()));
+        assert_eq!(request(&server, "POST", "/submit", "dave 7 5 0 1 2 3 4 5").0, 403);
+        assert_eq!(request(&server, "GET", "/top?seed=8", ""), (200, "carol 4\n".to_owned()));
+        assert_eq!(request(&server, "GET", "/top?seed=7", ""), (200, "bob 3\nalice 2\n".to_owned()));
+        // The all-time board spans both days.
+        assert_eq!(request(&server, "GET", "/top", ""), (200, "carol 4\nbob 3\nalice 2\n".to_owned()));
+        assert_eq!(request(&server, "GET", "/top?seed=x", "").0, 400);
+        assert_eq!(request(&server, "GET", "/elsewhere", "").0, 404);
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
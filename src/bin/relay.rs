@@ -0,0 +1,287 @@
+// Lobby and relay for online races. Players join a room by name, anyone in
+// it can start a race, and from then on every flap a player sends is passed
+// on to the rest of the room. The relay never simulates anything;
+// each game runs its own copy of the seeded course.
+//
+//   cargo run --bin relay [address]    (default 127.0.0.1:7777)
+//   cargo run -- --race 127.0.0.1:7777 --room test --name alice
+
+#[path = "../protocol.rs"]
+mod protocol;
+
+use std::collections::HashMap;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use protocol::{Member, Message, DEFAULT_PORT, MAX_PACKET};
+
+// Players that haven't sent anything for this long are dropped.
+const TIMEOUT: Duration = Duration::from_secs(10);
+// Most players in a room. With names capped at MAX_WORD bytes, a full
+// room's lobby message fits in MAX_PACKET.
+const ROOM_SIZE: usize = 16;
+// A room's race is over once no racer has sent inputs for this long.
+const RACE_IDLE: Duration = Duration::from_secs(3);
+
+struct Client {
+    addr: SocketAddr,
+    id: u32,
+    name: String,
+    skin: String,
+    room: String,
+    last_seen: Instant,
+}
+
+struct Relay {
+    clients: Vec<Client>,
+    next_id: u32,
+    // Rooms with a race going, and when it last heard from a racer.
+    races: HashMap<String, Instant>,
+}
+
+impl Relay {
+    fn new() -> Relay {
+        Relay { clients: vec![], next_id: 1, races: HashMap::new() }
+    }
+
+    // What to send to whom in answer to `message` from `from`.
+    fn handle(&mut self, from: SocketAddr, message: Message, now: Instant, seed: u64) -> Vec<(SocketAddr, Message)> {
+        let index = self.clients.iter().position(|client| client.addr == from);
+        if let Some(index) = index {
+            self.clients[index].last_seen = now;
+        }
+        match (message, index) {
+            (Message::Join { room, name, skin }, Some(index)) => {
+                let client = &self.clients[index];
+                if client.room == room && client.name == name && client.skin == skin {
+                    // Keep-alive, or the welcome got lost.
+                    return vec![(from, Message::Welcome { id: client.id })];
+                }
+                if client.room != room && self.full(&room) {
+                    return vec![];
+                }
+                let client = &mut self.clients[index];
+                let old = std::mem::replace(&mut client.room, room.clone());
+                client.name = name;
+                client.skin = skin;
+                let mut out = vec![(from, Message::Welcome { id: client.id })];
+                out.extend(self.lobby(&old));
+                out.extend(self.lobby(&room));
+                out
+            }
+            (Message::Join { room, .. }, None) if self.full(&room) => {
+                println!("{} is full, turning away {}", room, from);
+                vec![]
+            }
+            (Message::Join { room, name, skin }, None) => {
+                let id = self.next_id;
+                self.next_id += 1;
+                println!("{} joined {} as {} ({})", name, room, id, from);
+                self.clients.push(Client { addr: from, id, name, skin, room: room.clone(), last_seen: now });
+                let mut out = vec![(from, Message::Welcome { id })];
+                out.extend(self.lobby(&room));
+                out
+            }
+            (Message::Go, Some(index)) => {
+                let room = &self.clients[index].room;
+                // Racers would lose their race to a new one.
+                if self.races.contains_key(room) {
+                    return vec![];
+                }
+                println!("race in {} with seed {}", room, seed);
+                self.races.insert(room.clone(), now);
+                self.room(room).map(|client| (client.addr, Message::Race { seed })).collect()
+            }
+            (Message::Input { tick, flaps }, Some(index)) => {
+                let sender = &self.clients[index];
+                if let Some(heard) = self.races.get_mut(&sender.room) {
+                    *heard = now;
+                }
+                self.room(&sender.room)
+                    .filter(|client| client.id != sender.id)
+                    .map(|client| (client.addr, Message::Player { id: sender.id, tick, flaps: flaps.clone() }))
+                    .collect()
+            }
+            (Message::Leave, Some(index)) => self.drop_client(index),
+            // Anything else is either from a stranger or only sent by the relay.
+            _ => vec![],
+        }
+    }
+
+    // Drops players that went quiet, and ends races nobody is flying.
+    fn expire(&mut self, now: Instant) -> Vec<(SocketAddr, Message)> {
+        self.races.retain(|room, heard| {
+            let going = now.duration_since(*heard) <= RACE_IDLE;
+            if !going {
+                println!("race in {} is over", room);
+            }
+            going
+        });
+        let mut out = vec![];
+        while let Some(index) = self.clients.iter().position(|client| now.duration_since(client.last_seen) > TIMEOUT) {
+            out.extend(self.drop_client(index));
+        }
+        out
+    }
+
+    fn drop_client(&mut self, index: usize) -> Vec<(SocketAddr, Message)> {
+        let client = self.clients.remove(index);
+        println!("{} ({}) left {}", client.name, client.id, client.room);
+        let mut out: Vec<_> = self.room(&client.room).map(|other| (other.addr, Message::Left { id: client.id })).collect();
+        out.extend(self.lobby(&client.room));
+        out
+    }
+
+    fn room<'a>(&'a self, room: &'a str) -> impl Iterator<Item = &'a Client> + 'a {
+        self.clients.iter().filter(move |client| client.room == room)
+    }
+
+    fn full(&self, room: &str) -> bool {
+        self.room(room).count() >= ROOM_SIZE
+    }
+
+    // Tells everyone in `room` who is in it.
+    fn lobby(&self, room: &str) -> Vec<(SocketAddr, Message)> {
+        let players: Vec<Member> = self
+            .room(room)
+            .map(|client| Member { id: client.id, name: client.name.clone(), skin: client.skin.clone() })
+            .collect();
+        self.room(room).map(|client| (client.addr, Message::Lobby { players: players.clone() })).collect()
+    }
+}
+
+fn main() {
+    let addr = std::env::args().nth(1).unwrap_or_else(|| format!("127.0.0.1:{}", DEFAULT_PORT));
+    let socket = match UdpSocket::bind(&addr) {
+        Ok(socket) => socket,
+        Err(err) => {
+            println!("failed to bind {}: {}", addr, err);
+            std::process::exit(1);
+        }
+    };
+    socket.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    println!("relay listening on {}", addr);
+    run(&socket);
+}
+
+fn run(socket: &UdpSocket) {
+    let mut relay = Relay::new();
+    let mut buffer = [0; MAX_PACKET];
+    loop {
+        let now = Instant::now();
+        let mut out = relay.expire(now);
+        if let Ok((len, from)) = socket.recv_from(&mut buffer) {
+            let text = String::from_utf8_lossy(&buffer[..len]);
+            match Message::decode(&text) {
+                Some(message) => out.extend(relay.handle(from, message, now, seed())),
+                None => println!("bad packet from {}: {}", from, text.trim()),
+            }
+        }
+        for (to, message) in out {
+            if let Err(err) = socket.send_to(message.encode().as_bytes(), to) {
+                println!("failed to send to {}: {}", to, err);
+            }
+        }
+    }
+}
+
+fn seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(1, |time| time.as_nanos() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send(socket: &UdpSocket, relay: SocketAddr, message: Message) {
+        socket.send_to(message.encode().as_bytes(), relay).unwrap();
+    }
+
+    // Next message of the given kind, skipping lobby updates and the like.
+    fn receive(socket: &UdpSocket, matches: fn(&Message) -> bool) -> Message {
+        let mut buffer = [0; MAX_PACKET];
+        loop {
+            let (len, _) = socket.recv_from(&mut buffer).expect("no reply from relay");
+            let message = Message::decode(&String::from_utf8_lossy(&buffer[..len])).unwrap();
+            if matches(&message) {
+                return message;
+            }
+        }
+    }
+
+    #[test]
+    fn relays_a_race_between_two_clients_on_localhost() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let relay = server.local_addr().unwrap();
+        std::thread::spawn(move || run(&server));
+
+        let clients: Vec<UdpSocket> = (0..2).map(|_| UdpSocket::bind("127.0.0.1:0").unwrap()).collect();
+        let mut ids = vec![];
+        for (n, client) in clients.iter().enumerate() {
+            client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            send(client, relay, Message::Join { room: "test".to_owned(), name: format!("bird {}", n), skin: "classic".to_owned() });
+            match receive(client, |message| matches!(message, Message::Welcome { .. })) {
+                Message::Welcome { id } => ids.push(id),
+                _ => unreachable!(),
+            }
+        }
+        let lobby = receive(&clients[0], |message| matches!(message, Message::Lobby { players } if players.len() == 2));
+        let member = |n: usize| Member { id: ids[n], name: format!("bird_{}", n), skin: "classic".to_owned() };
+        assert_eq!(lobby, Message::Lobby { players: vec![member(0), member(1)] });
+
+        send(&clients[1], relay, Message::Go);
+        let seeds: Vec<Message> = clients.iter().map(|client| receive(client, |message| matches!(message, Message::Race { .. }))).collect();
+        assert_eq!(seeds[0], seeds[1]);
+
+        send(&clients[0], relay, Message::Input { tick: 42, flaps: vec![17, 30, 41] });
+        let relayed = receive(&clients[1], |message| matches!(message, Message::Player { .. }));
+        assert_eq!(relayed, Message::Player { id: ids[0], tick: 42, flaps: vec![17, 30, 41] });
+
+        send(&clients[0], relay, Message::Leave);
+        let left = receive(&clients[1], |message| matches!(message, Message::Left { .. }));
+        assert_eq!(left, Message::Left { id: ids[0] });
+    }
+
+    #[test]
+    fn a_full_room_of_long_names_fits_in_a_packet() {
+        let mut relay = Relay::new();
+        let now = Instant::now();
+        let mut lobbies = vec![];
+        for n in 0..=ROOM_SIZE {
+            let addr: SocketAddr = format!("127.0.0.1:{}", n + 1).parse().unwrap();
+            let name = format!("bird with a very long name ü{}", n).repeat(4);
+            let out = relay.handle(addr, Message::Join { room: "test".to_owned(), name, skin: "ä".repeat(40) }, now, 1);
+            // One more than fits is turned away.
+            assert_eq!(out.is_empty(), n == ROOM_SIZE);
+            lobbies.extend(out.into_iter().map(|(_, message)| message.encode()));
+        }
+        let text = lobbies.last().unwrap();
+        assert!(text.len() <= MAX_PACKET, "lobby is {} bytes", text.len());
+        let Some(Message::Lobby { players }) = Message::decode(text) else {
+            panic!("lobby didn't decode: {}", text);
+        };
+        assert_eq!(players.len(), ROOM_SIZE);
+        assert_eq!(players[0].name, "bird_with_a_very");
+        assert_eq!(players[0].skin, "ä".repeat(8));
+    }
+
+    #[test]
+    fn starts_one_race_at_a_time_per_room() {
+        let mut relay = Relay::new();
+        let (alice, bob): (SocketAddr, SocketAddr) = ("127.0.0.1:1".parse().unwrap(), "127.0.0.1:2".parse().unwrap());
+        let start = Instant::now();
+        for (n, addr) in [alice, bob].into_iter().enumerate() {
+            relay.handle(addr, Message::Join { room: "test".to_owned(), name: format!("bird{}", n), skin: "classic".to_owned() }, start, 1);
+        }
+        let races = |out: Vec<(SocketAddr, Message)>| out.into_iter().filter(|(_, message)| matches!(message, Message::Race { .. })).count();
+        assert_eq!(races(relay.handle(alice, Message::Go, start, 1)), 2);
+        // A second go while alice is still flying would restart her race.
+        let later = start + RACE_IDLE;
+        relay.handle(alice, Message::Input { tick: 100, flaps: vec![] }, later, 2);
+        assert_eq!(races(relay.handle(bob, Message::Go, later, 2)), 0);
+        // Once the racers go quiet the room can race again.
+        let after = later + RACE_IDLE * 2;
+        relay.expire(after);
+        assert_eq!(races(relay.handle(bob, Message::Go, after, 3)), 2);
+    }
+}
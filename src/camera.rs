@@ -0,0 +1,133 @@
+use macroquad::prelude::*;
+
+use crate::settings::Settings;
+
+const MAX_SHAKE_OFFSET: f32 = 24.0;
+const MAX_SHAKE_ANGLE: f32 = 3.0;
+const TRAUMA_DECAY: f32 = 1.5;
+
+const HIT_STOP: f32 = 0.12;
+const FLASH: f32 = 0.25;
+const SLOW_MOTION: f32 = 1.2;
+const SLOW_MOTION_SCALE: f32 = 0.35;
+const SLOW_MOTION_ZOOM: f32 = 1.4;
+
+// World camera with trauma-based shake and the death effects: hit-stop,
+// a white flash and a slow-motion zoom onto the bird. Effects that are
+// turned off in Settings are simply never started.
+pub struct GameCamera {
+    trauma: f32,
+    hit_stop: f32,
+    flash: f32,
+    slow_motion: f32,
+    step_acc: f32,
+}
+
+impl GameCamera {
+    pub fn new() -> GameCamera {
+        GameCamera {
+            trauma: 0.0,
+            hit_stop: 0.0,
+            flash: 0.0,
+            slow_motion: 0.0,
+            step_acc: 0.0,
+        }
+    }
+
+    pub fn add_trauma(&mut self, amount: f32, settings: &Settings) {
+        if settings.screen_shake {
+            self.trauma = (self.trauma + amount).min(1.0);
+        }
+    }
+
+    pub fn impact(&mut self, settings: &Settings) {
+        self.add_trauma(0.6, settings);
+        if settings.hit_stop {
+            self.hit_stop = HIT_STOP;
+        }
+        if settings.flash {
+            self.flash = FLASH;
+        }
+        if settings.death_zoom {
+            self.slow_motion = SLOW_MOTION;
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.trauma = (self.trauma - TRAUMA_DECAY * dt).max(0.0);
+        self.flash = (self.flash - dt).max(0.0);
+        if self.hit_stop > 0.0 {
+            self.hit_stop = (self.hit_stop - dt).max(0.0);
+        } else {
+            self.slow_motion = (self.slow_motion - dt).max(0.0);
+        }
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        if self.hit_stop > 0.0 {
+            0.0
+        } else if self.slow_motion > 0.0 {
+            SLOW_MOTION_SCALE
+        } else {
+            1.0
+        }
+    }
+
+    // The simulation runs in fixed per-frame steps, so slow motion is done
+    // by skipping steps: returns how many to run this frame.
+    pub fn steps(&mut self) -> u32 {
+        self.step_acc += self.time_scale();
+        let steps = self.step_acc.floor();
+        self.step_acc -= steps;
+        steps as u32
+    }
+
+    // Sets the world camera for this frame, zooming towards `focus` while
+    // slow motion is active. Draws into `render_target` instead of the window
+    // when one is given.
+    pub fn set(&self, focus: Vec2, render_target: Option<&RenderTarget>) {
+        let center = vec2(screen_width(), screen_height()) / 2.0;
+        let zoom_in = (self.slow_motion / SLOW_MOTION).min(1.0);
+        let zoom = 1.0 + (SLOW_MOTION_ZOOM - 1.0) * zoom_in;
+        let mut target = center.lerp(focus, zoom_in);
+
+        // The shake rolls share macroquad's RNG with the seeded course, so
+        // only roll while shaking.
+        let shake = self.trauma * self.trauma;
+        let mut rotation = 0.0;
+        if shake > 0.0 {
+            target += vec2(rand::gen_range(-1.0, 1.0), rand::gen_range(-1.0, 1.0)) * MAX_SHAKE_OFFSET * shake;
+            rotation = rand::gen_range(-1.0, 1.0) * MAX_SHAKE_ANGLE * shake;
+        }
+
+        set_camera(&Camera2D {
+            target,
+            // Positive y zoom keeps y pointing down when drawing to the screen.
+            zoom: vec2(2.0 / screen_width(), 2.0 / screen_height()) * zoom,
+            rotation,
+            render_target: render_target.cloned(),
+            ..Default::default()
+        });
+    }
+
+    pub fn draw_flash(&self) {
+        if self.flash > 0.0 {
+            draw_rectangle(0.0, 0.0, screen_width(), screen_height(), Color::new(1.0, 1.0, 1.0, self.flash / FLASH));
+        }
+    }
+}
+
+// Screen coordinates for text and overlays, like set_default_camera() but
+// into `target` when there is one.
+pub fn set_screen_camera(target: Option<&RenderTarget>) {
+    let Some(target) = target else {
+        set_default_camera();
+        return;
+    };
+    set_camera(&Camera2D {
+        target: vec2(screen_width(), screen_height()) / 2.0,
+        zoom: vec2(2.0 / screen_width(), 2.0 / screen_height()),
+        render_target: Some(target.clone()),
+        ..Default::default()
+    });
+}
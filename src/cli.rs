@@ -0,0 +1,91 @@
+// Command line options.
+//
+//   --dev                  hot-reload assets (always on in debug builds)
+//   --seed <n>             fixed random seed instead of the clock
+//   --screenshot-at <tick> save a screenshot after that many frames and exit
+//   --golden <png>         with --screenshot-at: compare against this image
+//                          instead of saving, exit code 1 on mismatch
+//   --export <replay.txt>  render a saved daily run offscreen and exit
+//   --export-to <dir|file.gif> numbered PNGs or a GIF (default replay.gif)
+//   --export-fps <n>       frames per second to record (default 30)
+//   --export-scale <x>     size relative to the window (default 1)
+//   --edit <level>         open a file in assets/levels in the level editor
+//   --race <host:port>     race online through a relay (cargo run --bin relay)
+//   --room <name>          relay room to join (default lobby)
+//   --name <name>          shown to the other racers (default $USER)
+//   --leaderboard <host:port> submit daily challenge runs to a leaderboard
+//                          (cargo run --features leaderboard --bin leaderboard)
+//   --verify               read a replay from stdin, print the score it really
+//                          makes and exit, code 1 if it doesn't match
+pub struct Args {
+    pub dev: bool,
+    pub seed: Option<u64>,
+    pub screenshot_at: Option<u64>,
+    pub golden: Option<String>,
+    pub export: Option<String>,
+    pub export_to: String,
+    pub export_fps: u32,
+    pub export_scale: f32,
+    pub edit: Option<String>,
+    pub race: Option<String>,
+    pub room: String,
+    pub name: String,
+    pub leaderboard: Option<String>,
+    pub verify: bool,
+}
+
+impl Default for Args {
+    fn default() -> Args {
+        Args {
+            dev: false,
+            seed: None,
+            screenshot_at: None,
+            golden: None,
+            export: None,
+            export_to: "replay.gif".to_owned(),
+            export_fps: 30,
+            export_scale: 1.0,
+            edit: None,
+            race: None,
+            room: "lobby".to_owned(),
+            name: std::env::var("USER").unwrap_or_else(|_| "player".to_owned()),
+            leaderboard: None,
+            verify: false,
+        }
+    }
+}
+
+impl Args {
+    pub fn parse() -> Args {
+        let mut args = Args::default();
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--dev" => args.dev = true,
+                "--seed" => args.seed = number(&arg, iter.next()),
+                "--screenshot-at" => args.screenshot_at = number(&arg, iter.next()),
+                "--golden" => args.golden = iter.next(),
+                "--export" => args.export = iter.next(),
+                "--export-to" => args.export_to = iter.next().unwrap_or(args.export_to),
+                "--export-fps" => args.export_fps = number(&arg, iter.next()).unwrap_or(args.export_fps),
+                "--export-scale" => args.export_scale = number(&arg, iter.next()).unwrap_or(args.export_scale),
+                "--edit" => args.edit = iter.next(),
+                "--race" => args.race = iter.next(),
+                "--room" => args.room = iter.next().unwrap_or(args.room),
+                "--name" => args.name = iter.next().unwrap_or(args.name),
+                "--leaderboard" => args.leaderboard = iter.next(),
+                "--verify" => args.verify = true,
+                _ => println!("unknown argument: {}", arg),
+            }
+        }
+        args
+    }
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Option<T> {
+    let parsed = value.as_deref().and_then(|v| v.parse().ok());
+    if parsed.is_none() {
+        println!("{} expects a number", flag);
+    }
+    parsed
+}
@@ -0,0 +1,76 @@
+use macroquad::prelude::*;
+
+use crate::pipe::{pipe_speed, Pipe};
+use crate::reach::reachable_through;
+
+pub const COIN_RADIUS: f32 = 14.0;
+
+// Chance of a coin coming with each generated pipe.
+const COIN_CHANCE: f32 = 0.5;
+
+#[derive(Clone, Copy)]
+pub struct Coin {
+    pub pos: Vec2,
+    pub taken: bool,
+}
+
+impl Coin {
+    pub fn new(pos: Vec2) -> Coin {
+        Coin { pos, taken: false }
+    }
+
+    // Scrolls with the pipes.
+    pub fn go(&mut self, score: f32, game_speed: f32) {
+        self.pos.x -= pipe_speed(score) * game_speed;
+    }
+
+    // Pulls the coin towards `target` if it's within `range` pixels.
+    pub fn attract(&mut self, target: Vec2, range: f32, game_speed: f32) {
+        let to = target - self.pos;
+        if to.length() < range {
+            self.pos += to.normalize_or_zero() * (12.0 * game_speed).min(to.length());
+        }
+    }
+
+    pub fn edge(&self) -> bool {
+        self.pos.x < -COIN_RADIUS
+    }
+
+    pub fn touches(&self, center: Vec2, radius: f32) -> bool {
+        self.pos.distance(center) < radius + COIN_RADIUS
+    }
+
+    // Spins by squashing horizontally; `time` in seconds.
+    pub fn draw(&self, time: f32) {
+        let w = COIN_RADIUS * (time * 4.0 + self.pos.x * 0.01).cos().abs().max(0.15);
+        draw_ellipse(self.pos.x, self.pos.y, w, COIN_RADIUS, 0.0, GOLD);
+        draw_ellipse_lines(self.pos.x, self.pos.y, w, COIN_RADIUS, 0.0, 3.0, Color::from_hex(0xa86b00));
+        draw_ellipse(self.pos.x - w * 0.3, self.pos.y - COIN_RADIUS * 0.35, w * 0.25, COIN_RADIUS * 0.25, 0.0, Color::new(1.0, 1.0, 1.0, 0.7));
+    }
+}
+
+// Sometimes places a coin with a newly spawned pipe: halfway between the
+// previous gap and this one, or in the middle of this gap if it doesn't
+// move. Only coins the bird can collect on its way from `prev` through
+// `pipe` are kept (see reach.rs).
+pub fn place_coin(prev: &Pipe, pipe: &Pipe, score: i64, floor: f32) -> Option<Coin> {
+    if rand::gen_range(0.0, 1.0) >= COIN_CHANCE {
+        return None;
+    }
+    let gap_center = |pipe: &Pipe| {
+        let gaps = pipe.gaps();
+        let (top, bottom) = gaps[rand::gen_range(0, gaps.len())];
+        (top + bottom) / 2.0
+    };
+    let between = vec2((prev.body_upper.right() + pipe.body_upper.x) / 2.0, (gap_center(prev) + gap_center(pipe)) / 2.0);
+    let inside = vec2(pipe.body_upper.center().x, gap_center(pipe));
+
+    let mut spots = vec![between];
+    if !pipe.moving() {
+        spots.insert(rand::gen_range(0, 2), inside);
+    }
+    spots
+        .into_iter()
+        .find(|&spot| reachable_through(prev, pipe, score, floor, Some(spot)))
+        .map(Coin::new)
+}
@@ -0,0 +1,286 @@
+use macroquad::prelude::*;
+
+use crate::assets::Assets;
+use crate::coin::{Coin, COIN_RADIUS};
+use crate::ground::Ground;
+use crate::level::{Level, LevelPipe, LevelRun, LEVEL_DIR};
+use crate::locale::Strings;
+use crate::pipe::{build_pipe, Pipe, PipeKind, MAX_GAP, MIN_GAP};
+use crate::text::{ui_scale, Align, Fonts, TextStyle};
+use crate::{BIRD_SIZE, BIRD_X};
+
+// Pixels per second the view scrolls with the arrow keys, and per wheel
+// notch.
+const SCROLL_SPEED: f32 = 600.0;
+const WHEEL_STEP: f32 = 60.0;
+
+// How close (in pixels) the mouse has to be to a gap edge to resize it.
+const EDGE_GRAB: f32 = 12.0;
+
+const NEW_GAP: f32 = 200.0;
+const GAP_STEP: f32 = 10.0;
+
+const KINDS: [PipeKind; 5] = [PipeKind::Static, PipeKind::Oscillating, PipeKind::Breathing, PipeKind::Drifting, PipeKind::DoubleGap];
+
+#[derive(Clone, Copy)]
+struct Item {
+    // Distance from the start of the level.
+    x: f32,
+    pipe: LevelPipe,
+}
+
+#[derive(Clone, Copy)]
+enum Drag {
+    // Mouse offset from the pipe's x and gap center.
+    Move { index: usize, grab: Vec2 },
+    Resize { index: usize },
+    Coin { index: usize },
+}
+
+// Places, drags and resizes the pipes of one level file (--edit). Pipes are
+// drawn with the game's renderer, and play-testing hands the level to a
+// LevelRun so it runs through the real game loop and collision.
+pub struct Editor {
+    file: String,
+    level: Level,
+    items: Vec<Item>,
+    // Distance from the start of the level and height.
+    coins: Vec<Vec2>,
+    // Level x at the left edge of the screen.
+    scroll: f32,
+    selected: Option<usize>,
+    drag: Option<Drag>,
+    // What the items look like in game, rebuilt when they change.
+    preview: Vec<Pipe>,
+    dirty: bool,
+}
+
+impl Editor {
+    pub fn open(file: &str) -> Editor {
+        let level = Level::load(file).unwrap_or_else(|| Level::new(file));
+        let mut editor = Editor {
+            file: file.to_owned(),
+            level: Level::new(file),
+            items: vec![],
+            coins: vec![],
+            scroll: -screen_width(),
+            selected: None,
+            drag: None,
+            preview: vec![],
+            dirty: true,
+        };
+        editor.set_level(level);
+        editor
+    }
+
+    fn set_level(&mut self, level: Level) {
+        self.items = level.positions().into_iter().zip(&level.pipes).map(|(x, &pipe)| Item { x, pipe }).collect();
+        self.coins = level.coins.clone();
+        self.level = level;
+        self.selected = None;
+        self.drag = None;
+        self.dirty = true;
+    }
+
+    // The level as edited, pipes in order with spacing worked out from
+    // their positions.
+    fn level(&self) -> Level {
+        let mut items = self.items.clone();
+        items.sort_by(|a, b| a.x.total_cmp(&b.x));
+        let mut last = 0.0;
+        let pipes = items
+            .iter()
+            .map(|item| {
+                let spacing = item.x - last;
+                last = item.x;
+                LevelPipe { spacing, ..item.pipe }
+            })
+            .collect();
+        let mut coins = self.coins.clone();
+        coins.sort_by(|a, b| a.x.total_cmp(&b.x));
+        Level { pipes, coins, ..self.level.clone() }
+    }
+
+    // Starts a play-test from the current view.
+    pub fn playtest(&self) -> LevelRun {
+        LevelRun::new(self.level(), None, self.scroll)
+    }
+
+    // Handles input for a frame. Returns true when a play-test should start.
+    pub fn update(&mut self, floor: f32, gap_scale: f32) -> bool {
+        let w = screen_width() / 10.0;
+        let (mouse_x, mouse_y) = mouse_position();
+        let x = mouse_x + self.scroll;
+
+        if is_key_down(KeyCode::Right) || is_key_down(KeyCode::D) {
+            self.scroll += SCROLL_SPEED * get_frame_time();
+        }
+        if is_key_down(KeyCode::Left) || is_key_down(KeyCode::A) {
+            self.scroll -= SCROLL_SPEED * get_frame_time();
+        }
+        let wheel = mouse_wheel().1;
+        if wheel != 0.0 {
+            self.scroll -= wheel.signum() * WHEEL_STEP;
+        }
+
+        let hovered = self.items.iter().rposition(|item| x >= item.x && x <= item.x + w);
+        let hovered_coin = self.coins.iter().rposition(|coin| coin.distance(vec2(x, mouse_y)) < COIN_RADIUS + 4.0);
+        if is_key_pressed(KeyCode::C) {
+            self.coins.push(vec2(x, mouse_y));
+        }
+        if is_mouse_button_pressed(MouseButton::Left) {
+            self.drag = match (hovered_coin, hovered) {
+                (Some(index), _) => Some(Drag::Coin { index }),
+                (None, Some(index)) => {
+                    let pipe = &self.items[index].pipe;
+                    let edge = (mouse_y - (pipe.center - pipe.gap / 2.0)).abs().min((mouse_y - (pipe.center + pipe.gap / 2.0)).abs());
+                    if edge < EDGE_GRAB {
+                        Some(Drag::Resize { index })
+                    } else {
+                        Some(Drag::Move { index, grab: vec2(x - self.items[index].x, mouse_y - pipe.center) })
+                    }
+                }
+                (None, None) => {
+                    let kind = self.selected.map_or(PipeKind::Static, |index| self.items[index].pipe.kind);
+                    self.items.push(Item { x: x - w / 2.0, pipe: LevelPipe { spacing: 0.0, center: mouse_y, gap: NEW_GAP, kind } });
+                    Some(Drag::Move { index: self.items.len() - 1, grab: vec2(w / 2.0, 0.0) })
+                }
+            };
+            self.selected = match self.drag {
+                Some(Drag::Move { index, .. } | Drag::Resize { index }) => Some(index),
+                _ => None,
+            };
+            self.dirty = true;
+        }
+        if is_mouse_button_released(MouseButton::Left) {
+            self.drag = None;
+        }
+        match self.drag {
+            Some(Drag::Move { index, grab }) => {
+                let item = &mut self.items[index];
+                item.x = (x - grab.x).max(0.0);
+                item.pipe.center = (mouse_y - grab.y).clamp(0.0, floor);
+                self.dirty = true;
+            }
+            Some(Drag::Resize { index }) => {
+                let pipe = &mut self.items[index].pipe;
+                pipe.gap = (2.0 * (mouse_y - pipe.center).abs()).clamp(MIN_GAP / 2.0, MAX_GAP * 2.0);
+                self.dirty = true;
+            }
+            Some(Drag::Coin { index }) => self.coins[index] = vec2(x, mouse_y.clamp(0.0, floor)),
+            None => {}
+        }
+
+        if is_mouse_button_pressed(MouseButton::Right) {
+            if let Some(index) = hovered_coin {
+                self.coins.remove(index);
+                self.drag = None;
+            }
+        }
+        let remove = if is_mouse_button_pressed(MouseButton::Right) && hovered_coin.is_none() {
+            hovered
+        } else if is_key_pressed(KeyCode::Delete) || is_key_pressed(KeyCode::Backspace) {
+            self.selected
+        } else {
+            None
+        };
+        if let Some(index) = remove {
+            self.items.remove(index);
+            self.selected = None;
+            self.drag = None;
+            self.dirty = true;
+        }
+
+        if let Some(index) = self.selected {
+            let pipe = &mut self.items[index].pipe;
+            if is_key_pressed(KeyCode::K) {
+                let current = KINDS.iter().position(|&kind| kind == pipe.kind).unwrap_or(0);
+                pipe.kind = KINDS[(current + 1) % KINDS.len()];
+                self.dirty = true;
+            }
+            if is_key_pressed(KeyCode::Up) {
+                pipe.gap += GAP_STEP;
+                self.dirty = true;
+            }
+            if is_key_pressed(KeyCode::Down) {
+                pipe.gap = (pipe.gap - GAP_STEP).max(MIN_GAP / 2.0);
+                self.dirty = true;
+            }
+        }
+
+        if is_key_pressed(KeyCode::S) && self.level().save(&self.file) {
+            println!("saved {}/{}", LEVEL_DIR, self.file);
+        }
+        if is_key_pressed(KeyCode::L) {
+            if let Some(level) = Level::load(&self.file) {
+                self.set_level(level);
+                println!("loaded {}/{}", LEVEL_DIR, self.file);
+            }
+        }
+
+        if self.dirty {
+            self.preview = self.items.iter().map(|item| build_pipe(item.pipe.kind, item.x, w, item.pipe.center, Some(item.pipe.gap), floor, gap_scale)).collect();
+            self.dirty = false;
+        }
+        is_key_pressed(KeyCode::P)
+    }
+
+    pub fn draw(&self, assets: &Assets, ground: &Ground, fonts: &Fonts, strings: &Strings) {
+        let w = screen_width() / 10.0;
+        let floor = ground.top();
+        for (item, preview) in self.items.iter().zip(&self.preview) {
+            let x = item.x - self.scroll;
+            if x + w < 0.0 || x > screen_width() {
+                continue;
+            }
+            let mut pipe = preview.clone();
+            for rect in [&mut pipe.body_upper, &mut pipe.body_lower].into_iter().chain(&mut pipe.body_middle) {
+                rect.x = x;
+            }
+            pipe.draw(assets);
+        }
+        ground.draw(&assets.ground, WHITE);
+        for &coin in &self.coins {
+            Coin::new(coin - vec2(self.scroll, 0.0)).draw(get_time() as f32);
+        }
+
+        // Authored gaps on top, since moving pipes are previewed mid-motion.
+        let scale = ui_scale();
+        for (index, item) in self.items.iter().enumerate() {
+            let x = item.x - self.scroll;
+            let color = if Some(index) == self.selected { YELLOW } else { Color::new(1.0, 1.0, 1.0, 0.6) };
+            draw_rectangle_lines(x, item.pipe.center - item.pipe.gap / 2.0, w, item.pipe.gap, 3.0, color);
+            fonts.draw(&format!("{} {}", item.pipe.kind.name(), item.pipe.gap.round()), &TextStyle {
+                size: 16.0,
+                color,
+                align: Align::Center,
+                offset: vec2(x + w / 2.0, item.pipe.center) / scale,
+                outline: Some((1.0, BLACK)),
+                ..Default::default()
+            });
+        }
+
+        let finish = self.level().positions().last().copied().unwrap_or(0.0) + self.level.finish - self.scroll;
+        draw_line(finish, 0.0, finish, floor, 4.0, WHITE);
+        let start = -self.scroll;
+        draw_line(start, 0.0, start, floor, 2.0, GREEN);
+        // Where the bird starts a play-test.
+        draw_circle_lines(BIRD_X + BIRD_SIZE / 2.0, (screen_height() + BIRD_SIZE) / 2.0, BIRD_SIZE / 2.0, 2.0, WHITE);
+
+        fonts.draw(&format!("{}/{}", LEVEL_DIR, self.file), &TextStyle {
+            size: 22.0,
+            offset: vec2(20.0, 20.0),
+            outline: Some((2.0, BLACK)),
+            ..Default::default()
+        });
+        fonts.draw(strings.get("editor.hint"), &TextStyle {
+            size: 16.0,
+            align: Align::Center,
+            anchor: vec2(0.5, 1.0),
+            offset: vec2(0.0, -20.0),
+            outline: Some((2.0, BLACK)),
+            max_width: Some(780.0),
+            ..Default::default()
+        });
+    }
+}
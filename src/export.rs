@@ -0,0 +1,254 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use macroquad::prelude::*;
+
+use crate::cli::Args;
+
+// The game steps once per frame, which is assumed to be 60 per second.
+const TICK_RATE: u64 = 60;
+
+enum Output {
+    // Numbered PNGs, frame00000.png, frame00001.png, ...
+    Frames(PathBuf),
+    Gif(GifWriter),
+}
+
+// Renders a replay offscreen and writes it out as a PNG sequence or an
+// animated GIF, picked by the --export-to path: anything ending in .gif is
+// a GIF, anything else a directory. The game draws into `target`, which is
+// the window size times --export-scale.
+pub struct FrameExport {
+    output: Output,
+    target: RenderTarget,
+    fps: u64,
+    written: u64,
+}
+
+impl FrameExport {
+    pub fn new(args: &Args) -> Option<FrameExport> {
+        args.export.as_ref()?;
+        let path = PathBuf::from(&args.export_to);
+        let output = if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gif")) {
+            match GifWriter::create(&path) {
+                Ok(gif) => Output::Gif(gif),
+                Err(err) => {
+                    println!("failed to create {}: {}", path.display(), err);
+                    return None;
+                }
+            }
+        } else {
+            if let Err(err) = std::fs::create_dir_all(&path) {
+                println!("failed to create {}: {}", path.display(), err);
+                return None;
+            }
+            Output::Frames(path)
+        };
+        let scale = args.export_scale.max(0.1);
+        let target = render_target((screen_width() * scale) as u32, (screen_height() * scale) as u32);
+        target.texture.set_filter(FilterMode::Nearest);
+        Some(FrameExport {
+            output,
+            target,
+            fps: (args.export_fps as u64).clamp(1, TICK_RATE),
+            written: 0,
+        })
+    }
+
+    pub fn target(&self) -> &RenderTarget {
+        &self.target
+    }
+
+    // Frame n shows the game at n / fps seconds, rounded down to a tick.
+    pub fn wants(&self, tick: u64) -> bool {
+        tick == self.written * TICK_RATE / self.fps
+    }
+
+    // Adds what has been drawn into the target this frame.
+    pub fn push(&mut self) -> std::io::Result<()> {
+        // Switching cameras flushes the draws still batched for the target.
+        set_default_camera();
+        let image = flip(&self.target.texture.get_texture_data());
+        match &mut self.output {
+            Output::Frames(dir) => {
+                let path = dir.join(format!("frame{:05}.png", self.written));
+                image.export_png(&path.to_string_lossy());
+            }
+            Output::Gif(gif) => {
+                // GIF delays are whole hundredths of a second, so they
+                // alternate to keep each frame at its exact time.
+                let at = |frame: u64| frame * 100 / self.fps;
+                gif.frame(&image, (at(self.written + 1) - at(self.written)) as u16)?;
+            }
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        if let Output::Gif(gif) = &mut self.output {
+            gif.finish()?;
+        }
+        println!("exported {} frames", self.written);
+        Ok(())
+    }
+
+    // Shows the frame in the window too, so it's clear something is
+    // happening.
+    pub fn show(&self) {
+        set_default_camera();
+        clear_background(BLACK);
+        draw_texture_ex(&self.target.texture, 0.0, 0.0, WHITE, DrawTextureParams {
+            dest_size: Some(vec2(screen_width(), screen_height())),
+            ..Default::default()
+        });
+    }
+}
+
+// Render targets read back top row first; the writers below take images
+// bottom row first, the way screenshot::capture() returns them.
+fn flip(image: &Image) -> Image {
+    let row = image.width as usize * 4;
+    let mut out = image.clone();
+    for (to, from) in out.bytes.chunks_exact_mut(row).zip(image.bytes.chunks_exact(row).rev()) {
+        to.copy_from_slice(from);
+    }
+    out
+}
+
+// Minimal GIF89a encoder. Every frame uses the same global palette, a
+// 6x7x6 color cube, which is plenty for the game's flat pixel art.
+struct GifWriter {
+    file: BufWriter<File>,
+    started: bool,
+}
+
+const LEVELS: (u32, u32, u32) = (6, 7, 6);
+
+impl GifWriter {
+    fn create(path: &PathBuf) -> std::io::Result<GifWriter> {
+        Ok(GifWriter { file: BufWriter::new(File::create(path)?), started: false })
+    }
+
+    fn header(&mut self, width: u16, height: u16) -> std::io::Result<()> {
+        let f = &mut self.file;
+        f.write_all(b"GIF89a")?;
+        f.write_all(&width.to_le_bytes())?;
+        f.write_all(&height.to_le_bytes())?;
+        // Global color table of 256 entries, 8 bits per channel.
+        f.write_all(&[0xf7, 0, 0])?;
+        for index in 0..256u32 {
+            let color = palette_color(index);
+            f.write_all(&color)?;
+        }
+        // Loop forever.
+        f.write_all(&[0x21, 0xff, 11])?;
+        f.write_all(b"NETSCAPE2.0")?;
+        f.write_all(&[3, 1, 0, 0, 0])
+    }
+
+    // `delay` is how long the frame stays up, in hundredths of a second.
+    fn frame(&mut self, image: &Image, delay: u16) -> std::io::Result<()> {
+        if !self.started {
+            self.header(image.width, image.height)?;
+            self.started = true;
+        }
+        let (w, h) = (image.width as usize, image.height as usize);
+        let data = image.get_image_data();
+        let mut indices = Vec::with_capacity(w * h);
+        for y in (0..h).rev() {
+            indices.extend(data[y * w..(y + 1) * w].iter().map(|&p| palette_index(p)));
+        }
+
+        let f = &mut self.file;
+        f.write_all(&[0x21, 0xf9, 4, 0])?;
+        f.write_all(&delay.to_le_bytes())?;
+        f.write_all(&[0, 0])?;
+        f.write_all(&[0x2c, 0, 0, 0, 0])?;
+        f.write_all(&image.width.to_le_bytes())?;
+        f.write_all(&image.height.to_le_bytes())?;
+        f.write_all(&[0, 8])?;
+        for block in lzw(&indices).chunks(255) {
+            f.write_all(&[block.len() as u8])?;
+            f.write_all(block)?;
+        }
+        f.write_all(&[0])
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        self.file.write_all(&[0x3b])?;
+        self.file.flush()
+    }
+}
+
+fn palette_color(index: u32) -> [u8; 3] {
+    let (lr, lg, lb) = LEVELS;
+    if index >= lr * lg * lb {
+        return [0, 0, 0];
+    }
+    let level = |n: u32, levels: u32| (n * 255 / (levels - 1)) as u8;
+    [level(index / (lg * lb), lr), level(index / lb % lg, lg), level(index % lb, lb)]
+}
+
+fn palette_index(pixel: [u8; 4]) -> u8 {
+    let (lr, lg, lb) = LEVELS;
+    let level = |c: u8, levels: u32| (c as u32 * (levels - 1) + 127) / 255;
+    (level(pixel[0], lr) * lg * lb + level(pixel[1], lg) * lb + level(pixel[2], lb)) as u8
+}
+
+// Variable-width LZW as GIF wants it, with 8-bit symbols.
+fn lzw(indices: &[u8]) -> Vec<u8> {
+    const CLEAR: u16 = 256;
+    const END: u16 = 257;
+
+    let mut out = Vec::new();
+    let (mut bits, mut nbits) = (0u32, 0u32);
+    let mut emit = |code: u16, width: u32, out: &mut Vec<u8>| {
+        bits |= (code as u32) << nbits;
+        nbits += width;
+        while nbits >= 8 {
+            out.push(bits as u8);
+            bits >>= 8;
+            nbits -= 8;
+        }
+    };
+
+    let mut table = std::collections::HashMap::new();
+    let mut next = END + 1;
+    let mut width = 9;
+    emit(CLEAR, width, &mut out);
+
+    let mut iter = indices.iter();
+    let Some(&first) = iter.next() else {
+        emit(END, width, &mut out);
+        emit(0, 7, &mut out);
+        return out;
+    };
+    let mut prefix = first as u16;
+    for &symbol in iter {
+        if let Some(&code) = table.get(&(prefix, symbol)) {
+            prefix = code;
+            continue;
+        }
+        emit(prefix, width, &mut out);
+        if next == 4096 {
+            emit(CLEAR, width, &mut out);
+            table.clear();
+            next = END + 1;
+            width = 9;
+        } else {
+            table.insert((prefix, symbol), next);
+            if next == 1 << width && width < 12 {
+                width += 1;
+            }
+            next += 1;
+        }
+        prefix = symbol as u16;
+    }
+    emit(prefix, width, &mut out);
+    emit(END, width, &mut out);
+    // Flush the last partial byte.
+    emit(0, 7, &mut out);
+    out
+}
@@ -0,0 +1,323 @@
+use macroquad::prelude::*;
+
+use crate::assets::ASSET_DIR;
+use crate::coin::{place_coin, Coin};
+use crate::pipe::{build_pipe, spawn_pipe, Pipe, PipeKind, MARGIN, MAX_GAP};
+use crate::reach::reachable;
+
+pub const PATTERN_FILE: &str = "patterns.txt";
+
+// Distance between pipes the original spawner used.
+const SPACING: (f32, f32) = (300.0, 500.0);
+
+// Tries at moving an unreachable gap before settling for the previous height.
+const REPAIR_STEPS: u32 = 4;
+
+#[derive(Clone, Copy)]
+pub struct Placement {
+    // Pixels between this pipe and the previous one.
+    pub spacing: f32,
+    // Where the middle of the gap goes.
+    pub center: f32,
+}
+
+// Range the gap center can be placed in.
+#[derive(Clone, Copy)]
+pub struct Bounds {
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl Bounds {
+    fn lerp(&self, t: f32) -> f32 {
+        self.top + (self.bottom - self.top) * t.clamp(0.0, 1.0)
+    }
+
+    fn clamp(&self, y: f32) -> f32 {
+        y.clamp(self.top, self.bottom)
+    }
+}
+
+// Decides where the next pipe goes. `last` is the pipe before it, or None
+// for the first pipe of a run.
+pub trait PipeGenerator {
+    fn next(&mut self, bounds: Bounds, last: Option<Placement>) -> Placement;
+}
+
+fn spacing() -> f32 {
+    rand::gen_range(SPACING.0, SPACING.1)
+}
+
+// The original behavior: any height, any spacing.
+pub struct UniformRandom;
+
+impl PipeGenerator for UniformRandom {
+    fn next(&mut self, bounds: Bounds, _last: Option<Placement>) -> Placement {
+        Placement { spacing: spacing(), center: rand::gen_range(bounds.top, bounds.bottom) }
+    }
+}
+
+// Each gap within `step` pixels of the previous one.
+pub struct RandomWalk {
+    pub step: f32,
+}
+
+impl PipeGenerator for RandomWalk {
+    fn next(&mut self, bounds: Bounds, last: Option<Placement>) -> Placement {
+        let center = match last {
+            Some(last) => bounds.clamp(last.center + rand::gen_range(-self.step, self.step)),
+            None => bounds.lerp(0.5),
+        };
+        Placement { spacing: spacing(), center }
+    }
+}
+
+// Evenly spaced gaps climbing or descending by `step`, turning around at
+// the top and bottom.
+pub struct Stairs {
+    pub step: f32,
+    down: bool,
+}
+
+impl Stairs {
+    pub fn new(step: f32) -> Stairs {
+        Stairs { step, down: rand::gen_range(0, 2) == 0 }
+    }
+}
+
+impl PipeGenerator for Stairs {
+    fn next(&mut self, bounds: Bounds, last: Option<Placement>) -> Placement {
+        let Some(last) = last else {
+            let center = if self.down { bounds.top } else { bounds.bottom };
+            return Placement { spacing: SPACING.0, center };
+        };
+        let mut center = last.center + if self.down { self.step } else { -self.step };
+        if center < bounds.top || center > bounds.bottom {
+            self.down = !self.down;
+            center = bounds.clamp(center);
+        }
+        Placement { spacing: SPACING.0, center }
+    }
+}
+
+// Alternates between high and low gaps.
+pub struct Zigzag {
+    // Fraction of the bounds either side of the middle.
+    pub amplitude: f32,
+    high: bool,
+}
+
+impl Zigzag {
+    pub fn new(amplitude: f32) -> Zigzag {
+        Zigzag { amplitude, high: false }
+    }
+}
+
+impl PipeGenerator for Zigzag {
+    fn next(&mut self, bounds: Bounds, _last: Option<Placement>) -> Placement {
+        self.high = !self.high;
+        let t = if self.high { 0.5 - self.amplitude } else { 0.5 + self.amplitude };
+        Placement { spacing: (SPACING.0 + SPACING.1) / 2.0, center: bounds.lerp(t) }
+    }
+}
+
+// Hand-authored sequences from assets/patterns.txt, picked at random and
+// played through one after the other.
+pub struct Chunks {
+    chunks: Vec<Vec<(f32, f32)>>,
+    current: usize,
+    index: usize,
+}
+
+impl Chunks {
+    pub fn load() -> Chunks {
+        let text = std::fs::read_to_string(format!("{}/{}", ASSET_DIR, PATTERN_FILE)).unwrap_or_else(|err| {
+            println!("failed to load {}: {}", PATTERN_FILE, err);
+            String::new()
+        });
+        let mut chunks: Vec<Vec<(f32, f32)>> = vec![];
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                chunks.push(vec![]);
+                continue;
+            }
+            let mut fields = line.split_whitespace().map(|v| v.parse::<f32>().ok());
+            match (chunks.last_mut(), fields.next().flatten(), fields.next().flatten(), fields.next()) {
+                (Some(chunk), Some(spacing), Some(height), None) => chunk.push((spacing, height)),
+                _ => println!("{}:{}: bad line: {}", PATTERN_FILE, n + 1, line),
+            }
+        }
+        chunks.retain(|chunk| !chunk.is_empty());
+        Chunks { chunks, current: 0, index: usize::MAX }
+    }
+}
+
+impl PipeGenerator for Chunks {
+    fn next(&mut self, bounds: Bounds, last: Option<Placement>) -> Placement {
+        if self.chunks.is_empty() {
+            return UniformRandom.next(bounds, last);
+        }
+        if self.index >= self.chunks[self.current].len() {
+            self.current = rand::gen_range(0, self.chunks.len());
+            self.index = 0;
+        }
+        let (spacing, height) = self.chunks[self.current][self.index];
+        self.index += 1;
+        Placement { spacing, center: bounds.lerp(height) }
+    }
+}
+
+// Switches to a different generator every few pipes.
+pub struct Mixed {
+    generators: Vec<Box<dyn PipeGenerator>>,
+    current: usize,
+    left: u32,
+}
+
+impl PipeGenerator for Mixed {
+    fn next(&mut self, bounds: Bounds, last: Option<Placement>) -> Placement {
+        if self.left == 0 {
+            self.current = rand::gen_range(0, self.generators.len());
+            self.left = rand::gen_range(5, 10);
+        }
+        self.left -= 1;
+        self.generators[self.current].next(bounds, last)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CourseKind {
+    Random,
+    Walk,
+    Stairs,
+    Zigzag,
+    Chunks,
+    Mixed,
+}
+
+impl CourseKind {
+    pub fn parse(value: &str) -> Option<CourseKind> {
+        match value {
+            "random" => Some(CourseKind::Random),
+            "walk" => Some(CourseKind::Walk),
+            "stairs" => Some(CourseKind::Stairs),
+            "zigzag" => Some(CourseKind::Zigzag),
+            "chunks" => Some(CourseKind::Chunks),
+            "mixed" => Some(CourseKind::Mixed),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CourseKind::Random => "random",
+            CourseKind::Walk => "walk",
+            CourseKind::Stairs => "stairs",
+            CourseKind::Zigzag => "zigzag",
+            CourseKind::Chunks => "chunks",
+            CourseKind::Mixed => "mixed",
+        }
+    }
+
+    pub fn generator(&self) -> Box<dyn PipeGenerator> {
+        match self {
+            CourseKind::Random => Box::new(UniformRandom),
+            CourseKind::Walk => Box::new(RandomWalk { step: 120.0 }),
+            CourseKind::Stairs => Box::new(Stairs::new(70.0)),
+            CourseKind::Zigzag => Box::new(Zigzag::new(0.35)),
+            CourseKind::Chunks => Box::new(Chunks::load()),
+            CourseKind::Mixed => Box::new(Mixed {
+                generators: [CourseKind::Random, CourseKind::Walk, CourseKind::Stairs, CourseKind::Zigzag, CourseKind::Chunks]
+                    .iter()
+                    .map(CourseKind::generator)
+                    .collect(),
+                current: 0,
+                left: 0,
+            }),
+        }
+    }
+}
+
+// Spawns pipes where the generator says, making sure the bird can get from
+// each gap to the next (see reach.rs).
+pub struct Course {
+    kind: CourseKind,
+    generator: Box<dyn PipeGenerator>,
+    last: Option<Placement>,
+    next: Option<Placement>,
+}
+
+impl Course {
+    pub fn new(kind: CourseKind) -> Course {
+        Course { kind, generator: kind.generator(), last: None, next: None }
+    }
+
+    // Starts over for a new run, also picking up a changed course setting.
+    pub fn reset(&mut self, kind: CourseKind) {
+        *self = Course::new(kind);
+    }
+
+    pub fn reload(&mut self, name: &str) {
+        if name == PATTERN_FILE {
+            self.reset(self.kind);
+            println!("reloaded {}", name);
+        }
+    }
+
+    // The first pipe comes in as soon as the run starts, the rest once the
+    // newest pipe has moved `spacing` pixels in from the right edge of a
+    // screen `width` wide. Returns the coin that goes with a new pipe, if
+    // any.
+    pub fn update(&mut self, pipes: &mut Vec<Pipe>, running: bool, score: i64, floor: f32, gap_scale: f32, width: f32) -> Option<Coin> {
+        let w = width / 10.0;
+        let top = MARGIN + MAX_GAP * gap_scale / 2.0;
+        let bounds = Bounds { top, bottom: (floor - MARGIN - MAX_GAP * gap_scale / 2.0).max(top) };
+        let mut next = *self.next.get_or_insert_with(|| {
+            let mut next = self.generator.next(bounds, self.last);
+            next.spacing = next.spacing.max(w * 2.0);
+            next.center = bounds.clamp(next.center);
+            next
+        });
+        let due = match pipes.last() {
+            Some(pipe) => pipe.body_lower.x < width - next.spacing,
+            None => running,
+        };
+        if !due {
+            return None;
+        }
+
+        let mut pipe = spawn_pipe(score, width, w, next.center, floor, gap_scale);
+        // Pulls gaps the bird can't get to towards the previous one, ending
+        // with a plain pipe at the same height.
+        if let (Some(prev), Some(last)) = (pipes.last(), self.last) {
+            let wanted = next.center;
+            for attempt in 1..=REPAIR_STEPS {
+                if reachable(prev, &pipe, score, floor) {
+                    break;
+                }
+                next.center = wanted + (last.center - wanted) * attempt as f32 / REPAIR_STEPS as f32;
+                pipe = if attempt < REPAIR_STEPS {
+                    spawn_pipe(score, width, w, next.center, floor, gap_scale)
+                } else {
+                    build_pipe(PipeKind::Static, width, w, next.center, None, floor, gap_scale)
+                };
+            }
+            // Even the plain pipe can be out of reach when the previous one
+            // moves. Holding it back a frame widens the spacing, which gives
+            // the bird more time, so try again then.
+            if !reachable(prev, &pipe, score, floor) {
+                self.next = Some(next);
+                return None;
+            }
+        }
+        let coin = pipes.last().and_then(|prev| place_coin(prev, &pipe, score, floor));
+        pipes.push(pipe);
+        self.last = Some(next);
+        self.next = None;
+        coin
+    }
+}
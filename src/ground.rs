@@ -0,0 +1,57 @@
+use macroquad::prelude::*;
+
+// Ground strip along the bottom of the screen. It scrolls at the pipe speed
+// and its top edge is the floor the bird collides with and lands on.
+pub struct Ground {
+    pub height: f32,
+    offset: f32,
+}
+
+impl Ground {
+    pub fn new(height: f32) -> Ground {
+        Ground { height, offset: 0.0 }
+    }
+
+    pub fn top(&self) -> f32 {
+        screen_height() - self.height
+    }
+
+    // Tiles are square so the sprite keeps its aspect at any ground height,
+    // and whole pixels wide so neighbouring tiles meet without a seam.
+    fn tile_width(&self) -> f32 {
+        self.height.round()
+    }
+
+    pub fn update(&mut self, speed: f32) {
+        let w = self.tile_width();
+        if w > 0.0 {
+            self.offset = (self.offset + speed).rem_euclid(w);
+        }
+    }
+
+    pub fn draw(&self, texture: &Texture2D, tint: Color) {
+        let w = self.tile_width();
+        if w <= 1.0 {
+            return;
+        }
+        let mut x = -self.offset.round();
+        while x < screen_width() {
+            draw_texture_ex(
+                texture,
+                x,
+                self.top(),
+                tint,
+                DrawTextureParams {
+                    dest_size: Some(vec2(w, self.height)),
+                    ..Default::default()
+                }
+            );
+            x += w;
+        }
+    }
+
+    pub fn draw_flat(&self, color: Color) {
+        draw_rectangle(0.0, self.top(), screen_width(), self.height, color);
+        draw_line(0.0, self.top(), screen_width(), self.top(), 6.0, WHITE);
+    }
+}
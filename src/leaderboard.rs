@@ -0,0 +1,146 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::replay::Replay;
+
+// Same as in src/bin/leaderboard.rs.
+const DEFAULT_PORT: u16 = 8787;
+const MAX_NAME: usize = 16;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Status {
+    Sending,
+    Accepted,
+    Rejected,
+    Failed,
+}
+
+impl Status {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Status::Sending => "board.sending",
+            Status::Accepted => "board.accepted",
+            Status::Rejected => "board.rejected",
+            Status::Failed => "board.failed",
+        }
+    }
+}
+
+enum Reply {
+    Top { global: bool, scores: Vec<(String, i64)> },
+    Submitted(Status),
+}
+
+// Talks to a leaderboard server (src/bin/leaderboard.rs) over plain HTTP.
+// Requests run on their own threads so the game never waits on the
+// network; answers are picked up by `poll`.
+pub struct Leaderboard {
+    pub server: String,
+    name: String,
+    // Best `name score` on today's seed and over every day.
+    pub daily: Vec<(String, i64)>,
+    pub global: Vec<(String, i64)>,
+    // How the last submission went.
+    pub status: Option<Status>,
+    sender: Sender<Reply>,
+    receiver: Receiver<Reply>,
+}
+
+impl Leaderboard {
+    // `server` is host:port, or just a host for the default port.
+    pub fn new(server: &str, name: &str) -> Leaderboard {
+        let server = if server.contains(':') { server.to_owned() } else { format!("{}:{}", server, DEFAULT_PORT) };
+        let (sender, receiver) = channel();
+        // Letters, digits, `_` and `-` only, like the server expects.
+        let name: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .take(MAX_NAME)
+            .collect();
+        let name = if name.is_empty() { "player".to_owned() } else { name };
+        Leaderboard { server, name, daily: vec![], global: vec![], status: None, sender, receiver }
+    }
+
+    // Fetches both top lists again.
+    pub fn refresh(&self, seed: u64) {
+        let (server, sender) = (self.server.clone(), self.sender.clone());
+        thread::spawn(move || fetch(&server, seed, &sender));
+    }
+
+    // Sends a finished run, then fetches the lists it may have changed.
+    pub fn submit(&mut self, replay: &Replay) {
+        self.status = Some(Status::Sending);
+        let (server, sender) = (self.server.clone(), self.sender.clone());
+        let (body, seed) = (format!("{} {}", self.name, replay.encode()), replay.seed);
+        thread::spawn(move || {
+            let status = match request(&server, "POST", "/submit", &body) {
+                Ok((200, _)) => Status::Accepted,
+                // Not today's course any more, or the replay didn't hold up.
+                Ok((403 | 422, _)) => Status::Rejected,
+                Ok((code, _)) => {
+                    println!("leaderboard {} answered {}", server, code);
+                    Status::Failed
+                }
+                Err(err) => {
+                    println!("failed to reach leaderboard {}: {}", server, err);
+                    Status::Failed
+                }
+            };
+            let _ = sender.send(Reply::Submitted(status));
+            fetch(&server, seed, &sender);
+        });
+    }
+
+    pub fn poll(&mut self) {
+        while let Ok(reply) = self.receiver.try_recv() {
+            match reply {
+                Reply::Top { global: false, scores } => self.daily = scores,
+                Reply::Top { global: true, scores } => self.global = scores,
+                Reply::Submitted(status) => self.status = Some(status),
+            }
+        }
+    }
+}
+
+fn fetch(server: &str, seed: u64, sender: &Sender<Reply>) {
+    for (global, path) in [(false, format!("/top?seed={}", seed)), (true, "/top".to_owned())] {
+        match request(server, "GET", &path, "") {
+            Ok((200, body)) => {
+                let _ = sender.send(Reply::Top { global, scores: parse_scores(&body) });
+            }
+            Ok((code, _)) => println!("leaderboard {} answered {} for {}", server, code, path),
+            Err(err) => {
+                println!("failed to reach leaderboard {}: {}", server, err);
+                return;
+            }
+        }
+    }
+}
+
+// Lines of `name score`.
+fn parse_scores(body: &str) -> Vec<(String, i64)> {
+    body.lines()
+        .filter_map(|line| {
+            let (name, score) = line.trim().rsplit_once(' ')?;
+            Some((name.to_owned(), score.parse().ok()?))
+        })
+        .collect()
+}
+
+// One HTTP/1.0 request; returns the status code and body.
+fn request(server: &str, method: &str, path: &str, body: &str) -> std::io::Result<(u16, String)> {
+    let addr = server.to_socket_addrs()?.next().ok_or(std::io::ErrorKind::NotFound)?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    write!(stream, "{} {} HTTP/1.0\r\nHost: {}\r\nContent-Length: {}\r\n\r\n{}", method, path, server, body.len(), body)?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let bad = || std::io::Error::new(std::io::ErrorKind::InvalidData, "bad response");
+    let (head, body) = response.split_once("\r\n\r\n").ok_or_else(bad)?;
+    let code = head.split_whitespace().nth(1).and_then(|code| code.parse().ok()).ok_or_else(bad)?;
+    Ok((code, body.to_owned()))
+}
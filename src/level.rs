@@ -0,0 +1,496 @@
+use std::collections::HashMap;
+use std::fs;
+
+use macroquad::prelude::*;
+
+use crate::assets::ASSET_DIR;
+use crate::coin::Coin;
+use crate::pipe::{build_pipe, pipe_speed, Pipe, PipeKind, MARGIN};
+use crate::reach::reachable;
+use crate::{BIRD_SIZE, BIRD_X};
+
+// Level files and campaign.txt, which lists them in play order.
+pub const LEVEL_DIR: &str = "levels";
+pub const CAMPAIGN_FILE: &str = "campaign.txt";
+// Best score, stars and completion per level, next to settings.txt.
+pub const PROGRESS_FILE: &str = "progress.txt";
+
+// Pixels from the last pipe to the finish line when a level doesn't say.
+const FINISH_SPACING: f32 = 400.0;
+
+// Length of levels made from a seed.
+const SEEDED_PIPES: usize = 50;
+
+#[derive(Clone, Copy)]
+pub struct LevelPipe {
+    // Pixels between this pipe and the previous one, or from the start of
+    // the level for the first.
+    pub spacing: f32,
+    // Where the middle of the gap goes.
+    pub center: f32,
+    // Gap size before the gap assist.
+    pub gap: f32,
+    pub kind: PipeKind,
+}
+
+// A hand-authored course from assets/levels, written as lines of
+//   name <text>
+//   stars <score> <score> <score>
+//   pipe <spacing> <gap y> <gap size> [kind]
+//   coin <offset> <y>
+//   finish <spacing>
+#[derive(Clone)]
+pub struct Level {
+    pub name: String,
+    pub pipes: Vec<LevelPipe>,
+    // Distance from the start of the level and height, in order. Written
+    // in the file as an offset from the pipe before.
+    pub coins: Vec<Vec2>,
+    // Scores needed for one, two and three stars, if not the default.
+    pub stars: Option<[i64; 3]>,
+    // Pixels from the last pipe to the finish line.
+    pub finish: f32,
+}
+
+impl Level {
+    pub fn load(file: &str) -> Option<Level> {
+        match fs::read_to_string(format!("{}/{}/{}", ASSET_DIR, LEVEL_DIR, file)) {
+            Ok(text) => Some(Level::parse(&text, file)),
+            Err(err) => {
+                println!("failed to load {}: {}", file, err);
+                None
+            }
+        }
+    }
+
+    fn parse(text: &str, file: &str) -> Level {
+        let mut level = Level::new(file);
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let ok = match fields.as_slice() {
+                ["name", ..] => {
+                    level.name = line["name".len()..].trim().to_owned();
+                    true
+                }
+                ["stars", a, b, c] => {
+                    level.stars = a.parse().ok().zip(b.parse().ok()).zip(c.parse().ok()).map(|((a, b), c)| [a, b, c]);
+                    level.stars.is_some()
+                }
+                ["pipe", spacing, center, gap, rest @ ..] => {
+                    let kind = match rest {
+                        [] => Some(PipeKind::Static),
+                        [kind] => PipeKind::parse(kind),
+                        _ => None,
+                    };
+                    match (spacing.parse(), center.parse(), gap.parse(), kind) {
+                        (Ok(spacing), Ok(center), Ok(gap), Some(kind)) => {
+                            level.pipes.push(LevelPipe { spacing, center, gap, kind });
+                            true
+                        }
+                        _ => false,
+                    }
+                }
+                ["coin", offset, y] => match (offset.parse::<f32>(), y.parse()) {
+                    (Ok(offset), Ok(y)) => {
+                        let x = level.positions().last().copied().unwrap_or(0.0);
+                        level.coins.push(vec2(x + offset, y));
+                        true
+                    }
+                    _ => false,
+                },
+                ["finish", spacing] => spacing.parse().map(|spacing| level.finish = spacing).is_ok(),
+                _ => false,
+            };
+            if !ok {
+                println!("{}:{}: bad line: {}", file, n + 1, line);
+            }
+        }
+        level.coins.sort_by(|a, b| a.x.total_cmp(&b.x));
+        level
+    }
+
+    // An empty level named after its file.
+    pub fn new(file: &str) -> Level {
+        Level { name: file.trim_end_matches(".txt").to_owned(), pipes: vec![], coins: vec![], stars: None, finish: FINISH_SPACING }
+    }
+
+    // A course made from `seed` alone, for online races and the daily
+    // challenge. It uses its own generator rather than macroquad's, so
+    // every game (and the leaderboard server) builds the same one.
+    pub fn seeded(seed: u64, floor: f32, width: f32) -> Level {
+        // A splitmix64 step first, so neighbouring seeds (the daily seed is
+        // the day number) start far apart. xorshift needs a nonzero state.
+        let mut state = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        state = (state ^ (state >> 31)).max(1);
+        // xorshift64, as a float in 0..1.
+        let mut random = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 40) as f32 / (1u64 << 24) as f32
+        };
+        let w = width / 10.0;
+        let mut level = Level::new(&format!("seed {}", seed));
+        let mut previous: Option<Pipe> = None;
+        let (mut x, mut center) = (0.0, floor / 2.0);
+        for n in 0..SEEDED_PIPES {
+            let spacing = 320.0 + 160.0 * random();
+            let gap = 210.0 - 50.0 * random() * (n as f32 / SEEDED_PIPES as f32);
+            x += spacing;
+            let (top, bottom) = (MARGIN + gap / 2.0, floor - MARGIN - gap / 2.0);
+            let mut target = (center + (random() - 0.5) * 320.0).clamp(top, bottom);
+            // Pulled back towards the last gap until the bird can make it.
+            let pipe = loop {
+                let pipe = build_pipe(PipeKind::Static, x, w, target, Some(gap), floor, 1.0);
+                if (target - center).abs() < 1.0 || previous.as_ref().is_none_or(|previous| reachable(previous, &pipe, n as i64, floor)) {
+                    break pipe;
+                }
+                target = (target + center) / 2.0;
+            };
+            level.pipes.push(LevelPipe { spacing, center: target, gap, kind: PipeKind::Static });
+            previous = Some(pipe);
+            center = target;
+        }
+        level
+    }
+
+    pub fn save(&self, file: &str) -> bool {
+        let mut text = format!("name {}\n", self.name);
+        if let Some([a, b, c]) = self.stars {
+            text += &format!("stars {} {} {}\n", a, b, c);
+        }
+        // Each coin goes after the last pipe before it.
+        let positions = self.positions();
+        let mut coins = self.coins.iter().peekable();
+        let mut write_coins = |text: &mut String, from: f32, until: Option<&f32>| {
+            while let Some(coin) = coins.next_if(|coin| until.is_none_or(|&until| coin.x < until)) {
+                *text += &format!("coin {} {}\n", (coin.x - from).round(), coin.y.round());
+            }
+        };
+        write_coins(&mut text, 0.0, positions.first());
+        for (index, pipe) in self.pipes.iter().enumerate() {
+            text += &format!("pipe {} {} {} {}\n", pipe.spacing.round(), pipe.center.round(), pipe.gap.round(), pipe.kind.name());
+            write_coins(&mut text, positions[index], positions.get(index + 1));
+        }
+        text += &format!("finish {}\n", self.finish.round());
+        match fs::write(format!("{}/{}/{}", ASSET_DIR, LEVEL_DIR, file), text) {
+            Ok(()) => true,
+            Err(err) => {
+                println!("failed to save {}: {}", file, err);
+                false
+            }
+        }
+    }
+
+    // Distance of each pipe from the start of the level.
+    pub fn positions(&self) -> Vec<f32> {
+        self.pipes
+            .iter()
+            .scan(0.0, |x, pipe| {
+                *x += pipe.spacing;
+                Some(*x)
+            })
+            .collect()
+    }
+
+    pub fn stars_for(&self, score: i64) -> u32 {
+        // Defaults to a third, two thirds and all of the pipes.
+        let count = self.pipes.len() as i64;
+        let stars = self.stars.unwrap_or([count / 3, count * 2 / 3, count]);
+        stars.iter().filter(|&&needed| score >= needed).count() as u32
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Progress {
+    pub best: i64,
+    pub stars: u32,
+    pub finished: bool,
+}
+
+// The levels in campaign.txt. Each one unlocks once the one before it has
+// been finished.
+pub struct Campaign {
+    files: Vec<String>,
+    pub levels: Vec<Level>,
+    progress: HashMap<String, Progress>,
+}
+
+impl Campaign {
+    pub fn load() -> Campaign {
+        let text = fs::read_to_string(format!("{}/{}/{}", ASSET_DIR, LEVEL_DIR, CAMPAIGN_FILE)).unwrap_or_else(|err| {
+            println!("failed to load {}: {}", CAMPAIGN_FILE, err);
+            String::new()
+        });
+        let mut files = vec![];
+        let mut levels = vec![];
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            if let Some(level) = Level::load(line) {
+                files.push(line.to_owned());
+                levels.push(level);
+            }
+        }
+        Campaign { files, levels, progress: load_progress() }
+    }
+
+    // Reads campaign.txt and the level files again after one of them
+    // changed; progress is kept.
+    pub fn reload(&mut self, name: &str) {
+        if name.strip_prefix(LEVEL_DIR).is_some_and(|rest| rest.starts_with('/')) {
+            let fresh = Campaign::load();
+            self.files = fresh.files;
+            self.levels = fresh.levels;
+            println!("reloaded {}", name);
+        }
+    }
+
+    pub fn progress(&self, index: usize) -> Progress {
+        self.progress.get(&self.files[index]).copied().unwrap_or_default()
+    }
+
+    pub fn unlocked(&self, index: usize) -> bool {
+        index == 0 || (index < self.levels.len() && self.progress(index - 1).finished)
+    }
+
+    // Steps through endless mode (None) and the unlocked levels.
+    pub fn cycle(&self, selected: Option<usize>, step: i32) -> Option<usize> {
+        let options = 1 + (0..self.levels.len()).take_while(|&index| self.unlocked(index)).count() as i32;
+        let current = selected.map_or(0, |index| index as i32 + 1);
+        match (current + step).rem_euclid(options) {
+            0 => None,
+            option => Some(option as usize - 1),
+        }
+    }
+
+    // Keeps the best score and stars of a run and saves progress.txt.
+    pub fn record(&mut self, index: usize, score: i64, finished: bool) {
+        let stars = self.levels[index].stars_for(score);
+        let progress = self.progress.entry(self.files[index].clone()).or_default();
+        progress.best = progress.best.max(score);
+        progress.stars = progress.stars.max(stars);
+        progress.finished |= finished;
+
+        let mut files: Vec<&String> = self.progress.keys().collect();
+        files.sort();
+        let text: String = files
+            .into_iter()
+            .map(|file| {
+                let progress = self.progress[file];
+                format!("{} = {} {} {}\n", file, progress.best, progress.stars, progress.finished)
+            })
+            .collect();
+        if let Err(err) = fs::write(PROGRESS_FILE, text) {
+            println!("failed to save {}: {}", PROGRESS_FILE, err);
+        }
+    }
+}
+
+// `level file = best stars finished` lines.
+fn load_progress() -> HashMap<String, Progress> {
+    let mut progress = HashMap::new();
+    let Ok(text) = fs::read_to_string(PROGRESS_FILE) else {
+        return progress;
+    };
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parsed = line.split_once('=').and_then(|(file, value)| {
+            let fields: Vec<&str> = value.split_whitespace().collect();
+            match fields.as_slice() {
+                [best, stars, finished] => Some((
+                    file.trim().to_owned(),
+                    Progress { best: best.parse().ok()?, stars: stars.parse().ok()?, finished: finished.parse().ok()? },
+                )),
+                _ => None,
+            }
+        });
+        match parsed {
+            Some((file, entry)) => {
+                progress.insert(file, entry);
+            }
+            None => println!("{}:{}: bad line: {}", PROGRESS_FILE, n + 1, line),
+        }
+    }
+    progress
+}
+
+// Plays one level: spawns its pipes as they scroll into view, then the
+// finish line.
+#[derive(Clone)]
+pub struct LevelRun {
+    pub level: Level,
+    // Campaign level being played, None when play-testing in the editor.
+    pub index: Option<usize>,
+    positions: Vec<f32>,
+    next: usize,
+    next_coin: usize,
+    // Level x at the left edge of the screen.
+    scroll: f32,
+    pub finished: bool,
+}
+
+impl LevelRun {
+    // `scroll` is where the left edge of the screen starts in the level;
+    // minus the screen width to play from the beginning. Pipes and coins
+    // that would start on top of or behind the bird are left out.
+    pub fn new(level: Level, index: Option<usize>, scroll: f32) -> LevelRun {
+        let positions = level.positions();
+        let next = positions.iter().take_while(|&&x| x - scroll < BIRD_X + BIRD_SIZE).count();
+        let next_coin = level.coins.iter().take_while(|coin| coin.x - scroll < BIRD_X + BIRD_SIZE).count();
+        LevelRun { level, index, positions, next, next_coin, scroll, finished: false }
+    }
+
+    // Scrolls along with the pipes.
+    pub fn go(&mut self, score: f32, game_speed: f32) {
+        self.scroll += pipe_speed(score) * game_speed;
+        self.finished |= self.finish_x() <= BIRD_X;
+    }
+
+    pub fn scroll(&self) -> f32 {
+        self.scroll
+    }
+
+    fn finish_x(&self) -> f32 {
+        self.positions.last().copied().unwrap_or(0.0) + self.level.finish - self.scroll
+    }
+
+    // Spawns every pipe and coin that has reached the right edge of a
+    // screen `width` wide.
+    pub fn update(&mut self, pipes: &mut Vec<Pipe>, coins: &mut Vec<Coin>, floor: f32, gap_scale: f32, width: f32) {
+        let w = width / 10.0;
+        while self.next < self.positions.len() && self.positions[self.next] - self.scroll <= width {
+            let pipe = &self.level.pipes[self.next];
+            let x = self.positions[self.next] - self.scroll;
+            pipes.push(build_pipe(pipe.kind, x, w, pipe.center, Some(pipe.gap), floor, gap_scale));
+            self.next += 1;
+        }
+        while let Some(&coin) = self.level.coins.get(self.next_coin).filter(|coin| coin.x - self.scroll <= width) {
+            coins.push(Coin::new(vec2(coin.x - self.scroll, coin.y)));
+            self.next_coin += 1;
+        }
+    }
+
+    pub fn draw(&self, floor: f32) {
+        let x = self.finish_x();
+        if x > screen_width() {
+            return;
+        }
+        // Checkered banner.
+        let size = 20.0;
+        for row in 0..(floor / size).ceil() as i32 {
+            for column in 0..2 {
+                let color = if (row + column) % 2 == 0 { WHITE } else { BLACK };
+                draw_rectangle(x + column as f32 * size, row as f32 * size, size, size.min(floor - row as f32 * size), color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::rand;
+
+    use super::{Campaign, Level, LevelRun};
+    use crate::coin::Coin;
+    use crate::pipe::Pipe;
+    use crate::reach::{reachable, reachable_through};
+
+    const WIDTH: f32 = 800.0;
+    const FLOOR: f32 = 536.0;
+
+    // Moving pipes start at a random phase, so each level is tried with a
+    // few seeds. Coins are checked with the two pipes around them.
+    #[test]
+    fn campaign_levels_are_winnable() {
+        let campaign = Campaign::load();
+        assert!(!campaign.levels.is_empty());
+        for (index, level) in campaign.levels.iter().enumerate() {
+            for seed in 0..50 {
+                rand::srand(seed);
+                let mut run = LevelRun::new(level.clone(), Some(index), -WIDTH);
+                let mut live: Vec<Pipe> = vec![];
+                let mut coins: Vec<Coin> = vec![];
+                let mut score = 0;
+                while !run.finished {
+                    let (count, before) = (live.len(), live.last().cloned());
+                    let mut spawned = vec![];
+                    run.update(&mut live, &mut spawned, FLOOR, 1.0, WIDTH);
+                    if live.len() > count {
+                        if let Some(prev) = before {
+                            let pipe = live.last().unwrap();
+                            assert!(reachable(&prev, pipe, score, FLOOR), "{}, seed {}: pipe {} can't be reached", level.name, seed, score + 1);
+                            for coin in coins.drain(..) {
+                                assert!(
+                                    reachable_through(&prev, pipe, score, FLOOR, Some(coin.pos)),
+                                    "{}, seed {}: coin before pipe {} can't be collected",
+                                    level.name,
+                                    seed,
+                                    score + 1
+                                );
+                            }
+                        }
+                        score += 1;
+                    }
+                    coins.extend(spawned);
+                    for pipe in &mut live {
+                        pipe.go(score as f32, 1.0);
+                    }
+                    for coin in &mut coins {
+                        coin.go(score as f32, 1.0);
+                    }
+                    run.go(score as f32, 1.0);
+                    live.retain(|pipe| !pipe.edge());
+                }
+                assert_eq!(score, level.pipes.len() as i64);
+            }
+        }
+    }
+
+    // Every game in a race builds the course on its own, so a seed has to
+    // give the same pipes each time, and they have to be passable.
+    #[test]
+    fn seeded_levels_are_repeatable_and_winnable() {
+        for seed in 0..30 {
+            let level = Level::seeded(seed, FLOOR, WIDTH);
+            let again = Level::seeded(seed, FLOOR, WIDTH);
+            assert!(level.pipes.iter().zip(&again.pipes).all(|(a, b)| (a.spacing, a.center, a.gap) == (b.spacing, b.center, b.gap)));
+
+            let mut run = LevelRun::new(level, None, -WIDTH);
+            let mut live: Vec<Pipe> = vec![];
+            let mut score = 0;
+            while !run.finished {
+                let (count, before) = (live.len(), live.last().cloned());
+                run.update(&mut live, &mut vec![], FLOOR, 1.0, WIDTH);
+                if live.len() > count {
+                    if let Some(prev) = before {
+                        assert!(reachable(&prev, live.last().unwrap(), score, FLOOR), "seed {}: pipe {} can't be reached", seed, score + 1);
+                    }
+                    score += 1;
+                }
+                for pipe in &mut live {
+                    pipe.go(score as f32, 1.0);
+                }
+                run.go(score as f32, 1.0);
+                live.retain(|pipe| !pipe.edge());
+            }
+        }
+    }
+
+    // The daily seed goes up by one a day, so tomorrow's course must not be
+    // today's.
+    #[test]
+    fn neighbouring_seeds_give_different_courses() {
+        for seed in 0..100 {
+            let (a, b) = (Level::seeded(seed, FLOOR, WIDTH), Level::seeded(seed + 1, FLOOR, WIDTH));
+            assert!(a.pipes.iter().zip(&b.pipes).any(|(a, b)| (a.spacing, a.center, a.gap) != (b.spacing, b.center, b.gap)), "seeds {} and {} match", seed, seed + 1);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use crate::assets::ASSET_DIR;
+
+const FALLBACK: &str = "en";
+
+// UI strings for one language, loaded from assets/strings.<lang>.txt as
+// `key = value` lines, where `{n}` stands for a number. Keys can
+// have plural variants `key.one`, `key.few`, `key.many` and `key.other`,
+// picked by `plural()` using the language's rules. Anything missing falls
+// back to English and then to the key itself.
+pub struct Strings {
+    pub language: String,
+    table: HashMap<String, String>,
+    fallback: HashMap<String, String>,
+}
+
+impl Strings {
+    // `setting` is the language from settings.txt; "auto" uses the
+    // environment locale.
+    pub fn load(setting: &str) -> Strings {
+        let language = if setting == "auto" { env_language() } else { setting.to_owned() };
+        let mut table = read_table(&language);
+        if table.is_empty() && language != FALLBACK {
+            println!("no strings for language {}, using {}", language, FALLBACK);
+        }
+        let fallback = if language == FALLBACK { std::mem::take(&mut table) } else { read_table(FALLBACK) };
+        Strings { language, table, fallback }
+    }
+
+    pub fn reload(&mut self, name: &str) {
+        if name == file_name(&self.language) || name == file_name(FALLBACK) {
+            let language = self.language.clone();
+            *self = Strings::load(&language);
+            println!("reloaded {}", name);
+        }
+    }
+
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.lookup(key).unwrap_or(key)
+    }
+
+    fn lookup(&self, key: &str) -> Option<&str> {
+        self.table.get(key).or_else(|| self.fallback.get(key)).map(String::as_str)
+    }
+
+    // Looks up the plural form of `key` for `n` and fills in `{n}` with
+    // the localized number.
+    pub fn plural(&self, key: &str, n: i64) -> String {
+        let form = format!("{}.{}", key, plural_form(&self.language, n));
+        let other = format!("{}.other", key);
+        let template = self.lookup(&form).or_else(|| self.lookup(&other)).unwrap_or(self.get(key));
+        template.replace("{n}", &self.number(n))
+    }
+
+    pub fn number(&self, n: i64) -> String {
+        let separator = match self.language.as_str() {
+            "de" | "es" | "it" | "nl" | "pt" => ".",
+            "fr" | "pl" | "ru" | "cs" | "sv" => " ",
+            _ => ",",
+        };
+        let digits = n.unsigned_abs().to_string();
+        let mut out = String::new();
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push_str(separator);
+            }
+            out.push(c);
+        }
+        if n < 0 {
+            out.insert(0, '-');
+        }
+        out
+    }
+}
+
+// CLDR plural categories for the languages we ship, simplified to integers.
+fn plural_form(language: &str, n: i64) -> &'static str {
+    let n = n.unsigned_abs();
+    match language {
+        "fr" => if n <= 1 { "one" } else { "other" },
+        "pl" => {
+            if n == 1 {
+                "one"
+            } else if (2..=4).contains(&(n % 10)) && !(12..=14).contains(&(n % 100)) {
+                "few"
+            } else {
+                "many"
+            }
+        }
+        "ru" | "uk" => {
+            if n % 10 == 1 && n % 100 != 11 {
+                "one"
+            } else if (2..=4).contains(&(n % 10)) && !(12..=14).contains(&(n % 100)) {
+                "few"
+            } else {
+                "many"
+            }
+        }
+        "ja" | "zh" | "ko" => "other",
+        _ => if n == 1 { "one" } else { "other" },
+    }
+}
+
+// "de_DE.UTF-8" -> "de", checked in the usual precedence order.
+fn env_language() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty() && value != "C" && value != "POSIX")
+        .and_then(|value| value.split(['_', '.', '@']).next().map(str::to_lowercase))
+        .unwrap_or_else(|| FALLBACK.to_owned())
+}
+
+fn file_name(language: &str) -> String {
+    format!("strings.{}.txt", language)
+}
+
+fn read_table(language: &str) -> HashMap<String, String> {
+    let mut table = HashMap::new();
+    let Ok(text) = std::fs::read_to_string(format!("{}/{}", ASSET_DIR, file_name(language))) else {
+        return table;
+    };
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((key, value)) => {
+                table.insert(key.trim().to_owned(), value.trim().to_owned());
+            }
+            None => println!("{}: bad line: {}", file_name(language), line),
+        }
+    }
+    table
+}
@@ -1,23 +1,164 @@
+mod accessibility;
+mod assets;
+mod audio;
+mod camera;
+mod cli;
+mod coin;
+mod editor;
+mod export;
+mod generator;
+mod ground;
+mod leaderboard;
+mod level;
+mod locale;
+mod music;
+mod net;
+mod parallax;
+mod particles;
+mod pipe;
+mod powerup;
+mod protocol;
+mod reach;
+mod replay;
+mod screenshot;
+mod settings;
+mod skins;
+mod sky;
+mod text;
+mod wallet;
+mod weather;
+
 use macroquad::prelude::*;
-use macroquad::audio::{load_sound, play_sound, Sound, PlaySoundParams};
+
+use accessibility::{AccessibilityMenu, Palette};
+use assets::{Assets, AssetWatcher};
+use audio::{AudioManager, Sfx};
+use camera::{set_screen_camera, GameCamera};
+use cli::Args;
+use coin::Coin;
+use editor::Editor;
+use export::FrameExport;
+use generator::Course;
+use ground::Ground;
+use leaderboard::Leaderboard;
+use level::{Campaign, Level, LevelRun};
+use locale::Strings;
+use music::MusicState;
+use net::NetClient;
+use parallax::Parallax;
+use particles::Particles;
+use pipe::{pipe_speed, Pipe};
+use powerup::{Hit, PowerUps, Powers};
+use replay::{daily_seed, Replay, REPLAY_FILE};
+use screenshot::SCREENSHOT_KEY;
+use settings::Settings;
+use skins::{Shop, Skins};
+use sky::DayCycle;
+use text::{ui_scale, Align, FontKind, Fonts, TextStyle};
+use wallet::Wallet;
+use weather::Weather;
+
+const BIRD_X: f32 = 100.0;
+const BIRD_SIZE: f32 = 60.0;
+// Per frame at game speed 1. The course validator in reach.rs simulates
+// the same physics.
+const GRAVITY: f32 = 0.5;
+const JUMP_VELOCITY: f32 = -8.0;
+
+// Versus mode: player 2 flies this far ahead of player 1, tinted so the
+// two can be told apart.
+const PLAYER_SPACING: f32 = 80.0;
+const PLAYER_TINTS: [Color; 2] = [WHITE, Color::new(0.55, 0.8, 1.0, 1.0)];
 
 struct Bird {
     body: Circle,
     velocity: f32,
+    // Where the bird flies; wind drifts it around this.
+    column: f32,
+    tint: Color,
+    score: i64,
+    alive: bool,
+    // Done for the round: dead and resting on the floor, or past a level's
+    // finish line.
+    landed: bool,
+    // Frames since the last flap, for the flapping sprite.
+    flapped: i32,
+    // Power-ups this bird picked up.
+    powers: Powers,
 }
 
 impl Bird {
-    fn fall(&mut self) {
-        self.velocity += 0.5;
-        self.body.y += self.velocity;
+    // `scale` is the game speed; the arc keeps its shape, it just takes longer.
+    fn fall(&mut self, scale: f32) {
+        self.velocity += GRAVITY * scale;
+        self.body.y += self.velocity * scale;
     }
     fn jump(&mut self) {
-        self.velocity = -8.0;  
+        self.velocity = JUMP_VELOCITY;  
     } 
+    // Hold-to-hover input: steady lift while the button is held instead
+    // of timed flaps.
+    fn hover(&mut self, scale: f32) {
+        self.velocity = (self.velocity - 0.9 * scale).max(-5.0);
+    }
+    // External forces such as wind. Horizontal drift is kept near the
+    // bird's usual column so the pipes stay fair.
+    fn push(&mut self, force: Vec2) {
+        self.velocity += force.y;
+        self.body.x = (self.body.x + force.x).clamp(self.column - 40.0, self.column + 100.0);
+    }
+    // Grows or shrinks a little towards `size`, keeping the same center.
+    fn resize(&mut self, size: f32) {
+        let center = self.center();
+        self.body.r = if self.body.r < size { (self.body.r + 2.0).min(size) } else { (self.body.r - 2.0).max(size) };
+        self.body.x = center.x - self.body.r/2.0;
+        self.body.y = center.y - self.body.r/2.0;
+    }
+    // Knocked back into the play area after a shield took the hit.
+    fn rebound(&mut self, hit: Hit, floor: f32) {
+        if hit == Hit::Ground {
+            self.body.y = floor - self.body.r - 1.0;
+            self.velocity = JUMP_VELOCITY;
+        } else {
+            self.body.y = 0.0;
+            self.velocity = 0.0;
+        }
+    }
+    // `color` in this bird's tint, for telling players apart.
+    fn tinted(&self, color: Color) -> Color {
+        Color::from_vec(color.to_vec() * self.tint.to_vec())
+    }
+    fn center(&self) -> Vec2 {
+        vec2(self.body.x + self.body.r/2.0, self.body.y + self.body.r/2.0)
+    }
+    fn sprite_height(&self) -> f32 {
+        self.body.r - 10.0
+    }
+    // Settles the dead bird on the floor with a small bounce.
+    // Returns true once it has come to rest.
+    fn land(&mut self, floor: f32) -> bool {
+        if self.body.y + self.sprite_height() < floor {
+            return false;
+        }
+        self.body.y = floor - self.sprite_height();
+        if self.velocity > 3.0 {
+            self.velocity *= -0.3;
+            false
+        } else {
+            self.velocity = 0.0;
+            true
+        }
+    }
     fn die(&mut self, pipes: &mut Vec<Pipe>, gameover: &bool) {
         if !gameover {
             self.velocity = 0.0;
+            self.body.r = BIRD_SIZE;
+            self.body.x = self.column;
             self.body.y = screen_height()/2.0;
+            self.score = 0;
+            self.alive = true;
+            self.landed = false;
+            self.powers = Powers::new();
             *pipes = vec![];
         }
         
@@ -28,29 +169,34 @@ fn make_bird(x: f32, y: f32, w: f32, velocity: f32) -> Bird {
     Bird {
         body: Circle::new(x, y, w),
         velocity,
+        column: x,
+        tint: WHITE,
+        score: 0,
+        alive: true,
+        landed: false,
+        flapped: 100,
+        powers: Powers::new(),
     }
 }
 
-#[derive(Clone)]
-struct Pipe {
-    body_upper: Rect,
-    body_lower: Rect,
-    scored: bool,
+fn make_birds(players: usize) -> Vec<Bird> {
+    (0..players)
+        .map(|n| Bird { tint: PLAYER_TINTS[n], ..make_bird(BIRD_X + n as f32 * PLAYER_SPACING, screen_height()/2.0, BIRD_SIZE, 0.0) })
+        .collect()
 }
-impl Pipe {
-    fn go(&mut self, score: f32) {
-        self.body_lower.x -= 5.0 + score as f32 /5.0;
-        self.body_upper.x -= 5.0 + score as f32 /5.0;
-    }
-    fn edge(&self) -> bool {
-        self.body_upper.x < 0.0 - self.body_upper.w
+
+// Flap buttons: Space or the mouse for player 1, Enter for player 2.
+fn flap_pressed(player: usize) -> bool {
+    match player {
+        0 => is_key_pressed(KeyCode::Space) || is_mouse_button_pressed(MouseButton::Left),
+        _ => is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter),
     }
 }
-fn build_pipe(x: f32, y: f32, w: f32, h: f32) -> Pipe {
-    Pipe {
-        body_lower: Rect::new(x, y+rand::gen_range(120.0, 250.0)+h, w, screen_height()),
-        body_upper: Rect::new(x, y, w, h),
-        scored: false,
+
+fn flap_down(player: usize) -> bool {
+    match player {
+        0 => is_key_down(KeyCode::Space) || is_mouse_button_down(MouseButton::Left),
+        _ => is_key_down(KeyCode::Enter) || is_key_down(KeyCode::KpEnter),
     }
 }
 
@@ -62,56 +208,194 @@ fn window_conf() -> Conf {
     }
 }
 
-#[macroquad::main{window_conf}]
-async fn main() {
-    rand::srand(macroquad::miniquad::date::now() as u64);
+fn main() {
+    let args = Args::parse();
+    // The leaderboard server checks replays this way, with no window.
+    if args.verify {
+        let mut text = String::new();
+        let _ = std::io::Read::read_to_string(&mut std::io::stdin(), &mut text);
+        let Some(replay) = Replay::decode(&text) else {
+            println!("bad replay");
+            std::process::exit(1);
+        };
+        let score = replay::simulate(replay.seed, &replay.flaps);
+        println!("{}", score);
+        std::process::exit(if score == replay.score { 0 } else { 1 });
+    }
+    macroquad::Window::from_config(window_conf(), game(args));
+}
+
+async fn game(args: Args) {
+    rand::srand(args.seed.unwrap_or(macroquad::miniquad::date::now() as u64));
 
-    let mut bird = make_bird(100.0, screen_height()/2.0, 60.0, 0.0);
+    let mut players = 1;
+    let mut birds = make_birds(players);
+    // The bird the camera follows: player 1, or whoever died last.
+    let mut focus = 0;
     let mut pipes: Vec<Pipe> = vec![];
+    let mut coins: Vec<Coin> = vec![];
+    let mut powerups = PowerUps::new();
     let mut i = 1;
-    let mut collision_detected = false;
-    let background = load_texture("assets/background.png").await.unwrap();
-    //background.set_filter(FilterMode::Linear);
-    let mut offset = 0.0;
-
-    let flappy = load_texture("assets/flappy.png").await.unwrap();
-    let flappy_jump = load_texture("assets/flappy_jump.png").await.unwrap();
-    let mut flappy_animation = 100;
-
-    let pipe_texture_upper = load_texture("assets/pipe_upper.png").await.unwrap();
-    let pipe_texture_lower = load_texture("assets/pipe_lower.png").await.unwrap();
-    let pipe_texture_body = load_texture("assets/pipe_body.png").await.unwrap();
-    let gobg = load_texture("assets/gobg.png").await.unwrap();
-
-    let flap: Sound = load_sound("assets/flap.ogg").await.unwrap();
-    let hit: Sound = load_sound("assets/hit.ogg").await.unwrap();
-    let point: Sound = load_sound("assets/point.ogg").await.unwrap();
-
-    let music: Sound = load_sound("assets/music.ogg").await.unwrap();
-    play_sound(&music, PlaySoundParams { looped: true, volume: 0.2 });
+    let mut assets = Assets::load().await;
+    let mut ground = Ground::new(64.0);
+    let mut particles = Particles::load().await;
+    let mut fonts = Fonts::load().await;
+    let mut settings = Settings::load();
+    let mut audio = AudioManager::load(&settings).await;
+    let mut menu = AccessibilityMenu::new();
+    let mut skins = Skins::load().await;
+    let mut parallax = Parallax::load(&skins.background().dir()).await;
+    let mut shop = Shop::new();
+    let mut strings = Strings::load(&settings.language);
+    let mut camera = GameCamera::new();
+    let mut sky = DayCycle::new(settings.day_cycle, settings.utc_offset);
+    let mut weather = Weather::new(settings.weather);
+    let mut course = Course::new(settings.course);
+    let mut campaign = Campaign::load();
+    // None plays the endless course.
+    let mut selected: Option<usize> = None;
+    let mut level_run: Option<LevelRun> = None;
+    let mut watcher = if AssetWatcher::enabled(&args) { Some(AssetWatcher::new()) } else { None };
+    // With --export, a saved run plays itself: its flaps stand in for
+    // player 1's and every frame is drawn offscreen for the export.
+    let mut playback = None;
+    if let Some(path) = &args.export {
+        let replay = Replay::load(path).unwrap_or_else(|| std::process::exit(1));
+        playback = Some(Replay { ticks: 0, ..replay });
+    }
+    let mut export = FrameExport::new(&args);
+    if args.export.is_some() && export.is_none() {
+        std::process::exit(1);
+    }
+    let target = export.as_ref().map(FrameExport::target).cloned();
+    let mut editor = args.edit.as_deref().map(Editor::open);
+    let mut net = args.race.as_deref().and_then(|server| NetClient::connect(server, &args.room, &args.name, &skins.current().id));
+    let mut leaderboard = args.leaderboard.as_deref().map(|server| Leaderboard::new(server, &args.name));
+    if let Some(leaderboard) = &leaderboard {
+        leaderboard.refresh(daily_seed());
+    }
+    // The daily challenge: a seeded course that's the same for everyone
+    // today. Runs on it are recorded to REPLAY_FILE, for --export and the
+    // leaderboard.
+    let mut daily = playback.is_some();
+    let mut recording: Option<Replay> = None;
 
     let mut score = 0;
     let mut highest_score = 0;
-    let mut scroll_speed = 1.0;
+    let mut highest_assisted_score = 0;
+    // Coins picked up this run. The wallet has them straight away but is
+    // only saved when the round ends, after a purchase, or on quit.
+    let mut run_coins = 0;
+    let mut wallet = Wallet::load();
 
     let mut running = false;
     let mut pressed = false;
     let mut gameover = false;
+    let mut tick: u64 = 0;
+    // Golden-image screenshots and exports step a fixed 60 FPS clock, so
+    // the picture doesn't depend on how fast the machine draws.
+    let fixed_clock = args.screenshot_at.is_some() || playback.is_some();
 
+    prevent_quit();
     loop {
-        clear_background(DARKBLUE);
-        
-        
-        draw_scrolling_background(&background, scroll_speed, &mut offset);
-        if gameover {
-            scroll_speed = 0.0;
-        } else {
-            scroll_speed = 1.0;
+        if is_quit_requested() {
+            wallet.save();
+            break;
+        }
+        if let Some(watcher) = &mut watcher {
+            for name in watcher.changed() {
+                assets.reload(&name).await;
+                audio.reload(&name).await;
+                parallax.reload(&name).await;
+                particles.reload(&name).await;
+                fonts.reload(&name).await;
+                strings.reload(&name);
+                course.reload(&name);
+                skins.reload(&name).await;
+                campaign.reload(&name);
+                selected = selected.filter(|&index| index < campaign.levels.len());
+            }
+        }
+
+        let (time, frame_time) = if fixed_clock { (tick as f64 / 60.0, 1.0 / 60.0) } else { (get_time(), get_frame_time()) };
+        let palette = Palette::new(&settings);
+        // Races and the daily challenge use the default physics so that
+        // other games (and the leaderboard's replay check) fly the same way.
+        let seeded = net.is_some() || daily;
+        let speed = if seeded { 1.0 } else { settings.game_speed };
+        let gap_assist = if seeded { 1.0 } else { settings.gap_assist };
+        let hover = settings.hold_to_hover && !seeded;
+        // Slow motion slows the course itself, so in versus it slows it
+        // for both birds.
+        let slowest = birds.iter().filter(|bird| bird.alive).map(|bird| bird.powers.effects().world_speed).fold(1.0, f32::min);
+        // Game speed for everything that scrolls with the pipes.
+        let scroll = speed * slowest;
+        let world_speed = pipe_speed(score as f32) * scroll;
+
+        // With --race the start screen is the lobby, and races start for
+        // everyone waiting there.
+        let lobby = net.is_some() && !running && !gameover;
+        if let Some(seed) = net.as_mut().and_then(|net| net.poll(lobby)) {
+            players = 1;
+            birds = make_birds(players);
+            focus = 0;
+            level_run = Some(LevelRun::new(Level::seeded(seed, ground.top(), screen_width()), None, -screen_width()));
+            running = true;
+            birds[0].jump();
+        }
+
+        camera.update(frame_time);
+        camera.set(birds[focus].center(), target.as_ref());
+        clear_background(if settings.high_contrast { palette.sky } else { sky.sky_color() });
+
+        // The editor replaces the start screen; play-tests and their game
+        // over screens run as usual.
+        if let Some(editor) = &mut editor {
+            if !running && !gameover {
+                if editor.update(ground.top(), settings.gap_assist) {
+                    level_run = Some(editor.playtest());
+                    running = true;
+                    for bird in &mut birds {
+                        bird.jump();
+                    }
+                } else {
+                    set_default_camera();
+                    editor.draw(&assets, &ground, &fonts, &strings);
+                    next_frame().await;
+                    continue;
+                }
+            } else if is_key_pressed(KeyCode::Escape) {
+                running = false;
+                gameover = false;
+                for bird in &mut birds {
+                    bird.die(&mut pipes, &gameover);
+                }
+                focus = 0;
+                coins.clear();
+                powerups.clear();
+                score = 0;
+                run_coins = 0;
+                level_run = None;
+                recording = None;
+            }
+        }
+
+        if !gameover {
+            parallax.update(world_speed);
+            ground.update(world_speed);
+            weather.update(frame_time * speed, world_speed);
+        }
+        if running {
+            sky.update(frame_time);
+        }
+        if !settings.high_contrast {
+            parallax.draw(sky.tint());
+            weather.draw();
         }
 
         if gameover && 0 > 1 {
             draw_texture_ex(
-                &gobg,
+                &assets.gobg,
                 0.0,
                 0.0,        
                 Color::from_rgba(255, 255, 255, 255),
@@ -123,208 +407,652 @@ async fn main() {
         }
 
         //HANDLE PIPES
+        // Pipe collisions this frame, per bird.
+        let mut hits = vec![false; birds.len()];
         for pipe in &mut pipes {
             if running{
-                pipe.go(score as f32);
+                pipe.go(score as f32, scroll);
             }
             
-            let mut y_lower = pipe.body_lower.y;
-            let mut y_upper = -pipe.body_upper.w;
-            //pipe.body_upper.h - pipe.body_upper.w;
-
-            draw_texture_ex(
-                &pipe_texture_lower,
-                pipe.body_lower.x,
-                pipe.body_lower.y,        
-                Color::from_rgba(255, 255, 255, 255),
-                DrawTextureParams {
-                    dest_size: Some(vec2(pipe.body_lower.w, pipe.body_lower.w)),
-                    ..Default::default()
-                }
-            );
-
-            while y_lower < screen_height() {
-                y_lower += pipe.body_lower.w - 1.0;
-                draw_texture_ex(
-                                &pipe_texture_body,
-                                pipe.body_lower.x,
-                                y_lower,        
-                                WHITE,
-                                DrawTextureParams {
-                                    dest_size: Some(vec2(pipe.body_lower.w, pipe.body_lower.w)),
-                                    ..Default::default()
-                                }
-                            );
-            }
-
-            while y_upper < pipe.body_upper.h - 2.0*pipe.body_upper.w {
-                y_upper += pipe.body_upper.w -1.0;
-                draw_texture_ex(
-                                &pipe_texture_body,
-                                pipe.body_upper.x,
-                                y_upper,        
-                                WHITE,
-                                DrawTextureParams {
-                                    dest_size: Some(vec2(pipe.body_upper.w, pipe.body_upper.w)),
-                                    ..Default::default()
-                                }
-                            );
+            if settings.high_contrast {
+                pipe.draw_flat(palette.pipe, palette.pipe_outline);
+            } else {
+                pipe.draw(&assets);
             }
 
-            draw_texture_ex(
-                &pipe_texture_upper,
-                pipe.body_upper.x,
-                pipe.body_upper.h - pipe.body_upper.w,        
-                WHITE,
-                DrawTextureParams {
-                    dest_size: Some(vec2(pipe.body_upper.w, pipe.body_upper.w)),
-                    ..Default::default()
-                }
-            );
-            //draw_circle(bird.body.x+ bird.body.r/2.0, bird.body.y+ bird.body.r/2.0, bird.body.r/2.0, RED);
             //draw_rectangle(pipe.body_lower.x, pipe.body_lower.y, pipe.body_lower.w, pipe.body_lower.h, BLUE);
             //draw_rectangle(pipe.body_upper.x, pipe.body_upper.y, pipe.body_upper.w, pipe.body_upper.h, BLUE);
 
-            if circle_rect_intersect(bird.body.x + bird.body.r/2.0, bird.body.y + bird.body.r/2.0, bird.body.r/2.0, pipe.body_lower.x, pipe.body_lower.y, pipe.body_lower.w, pipe.body_lower.h) || circle_rect_intersect(bird.body.x + bird.body.r/2.0, bird.body.y + bird.body.r/2.0, bird.body.r/2.0, pipe.body_upper.x, pipe.body_upper.y, pipe.body_upper.w, pipe.body_upper.h) {
-                println!("collission detected");
-                collision_detected = true;
+            for (n, bird) in birds.iter_mut().enumerate().filter(|(_, bird)| bird.alive) {
+                //draw_circle(bird.body.x+ bird.body.r/2.0, bird.body.y+ bird.body.r/2.0, bird.body.r/2.0, RED);
+                if pipe.passable & (1 << n) == 0 && pipe.solids().any(|rect| circle_rect_intersect(bird.body.x + bird.body.r/2.0, bird.body.y + bird.body.r/2.0, bird.body.r/2.0, rect.x, rect.y, rect.w, rect.h)) {
+                    if !gameover && bird.powers.absorb(Hit::Pipe) {
+                        pipe.passable |= 1 << n;
+                        particles.emit("powerup", bird.center());
+                        camera.add_trauma(0.3, &settings);
+                    } else {
+                        println!("collission detected");
+                        hits[n] = true;
+                    }
+                }
+
+                //COUNT SCORE
+                if bird.body.x > pipe.body_lower.x && pipe.scored & (1 << n) == 0 {
+                    bird.score += 1;
+                    pipe.scored |= 1 << n;
+                    particles.emit("sparkle", vec2(pipe.body_upper.x + pipe.body_upper.w/2.0, (pipe.body_upper.h + pipe.body_lower.y)/2.0));
+                    audio.play(Sfx::Point);
+                }
+            }
+        }
+        // Speed and records follow whoever is ahead.
+        score = birds.iter().map(|bird| bird.score).max().unwrap_or(0);
+        //HANDLE COINS
+        for coin in &mut coins {
+            if running {
+                coin.go(score as f32, scroll);
+                for bird in birds.iter().filter(|bird| bird.alive) {
+                    coin.attract(bird.center(), bird.powers.effects().magnet, speed);
+                }
+            }
+            if !gameover && birds.iter().any(|bird| bird.alive && coin.touches(bird.center(), bird.body.r/2.0)) {
+                coin.taken = true;
+                run_coins += 1;
+                wallet.add(1);
+                particles.emit("coin", coin.pos);
+                audio.play(Sfx::Coin);
             }
+            if !coin.taken {
+                coin.draw(time as f32);
+            }
+        }
+        coins.retain(|coin| !coin.taken && !coin.edge());
 
-            //COUNT SCORE
-            if bird.body.x > pipe.body_lower.x && !pipe.scored  {
-                score += 1;
-                pipe.scored = true;
-                play_sound(&point, PlaySoundParams { looped: false, volume: 0.1 });
+        if running {
+            powerups.go(score as f32, scroll);
+            for bird in birds.iter_mut().filter(|bird| bird.alive) {
+                bird.powers.update(frame_time * speed);
+                bird.resize(BIRD_SIZE * bird.powers.effects().bird_scale);
+            }
+        }
+        if !gameover {
+            for bird in birds.iter_mut().filter(|bird| bird.alive) {
+                if let Some((power, pos)) = powerups.collect(bird.center(), bird.body.r/2.0) {
+                    bird.powers.add(power);
+                    particles.emit("powerup", pos);
+                    audio.play(Sfx::Coin);
+                }
             }
+        }
+        powerups.draw(&fonts, time as f32);
 
+        let mut deaths = 0;
+        for (n, bird) in birds.iter_mut().enumerate().filter(|(_, bird)| bird.alive && !bird.landed) {
+            let hit = if hits[n] {
+                Some(Hit::Pipe)
+            } else if bird.body.y + bird.body.r > ground.top() {
+                Some(Hit::Ground)
+            } else if bird.body.y < 0.0 {
+                Some(Hit::Ceiling)
+            } else {
+                None
+            };
+            let Some(hit) = hit else {
+                continue;
+            };
+            if hit != Hit::Pipe && !gameover && bird.powers.absorb(hit) {
+                bird.rebound(hit, ground.top());
+                particles.emit("powerup", bird.center());
+                continue;
+            }
+            audio.play_as(Sfx::Hit, skins.current().death.as_ref());
+            particles.emit("death", bird.center());
+            bird.alive = false;
+            bird.powers = Powers::new();
+            focus = n;
+            deaths += 1;
+        }
+        // Hit-stop and the death zoom only for the last bird, so the other
+        // player can fly on.
+        if deaths > 0 && birds.iter().any(|bird| bird.alive) {
+            camera.add_trauma(0.5, &settings);
+        } else if deaths > 0 {
+            camera.impact(&settings);
         }
-        if (collision_detected || bird.body.y + bird.body.r > screen_height() || bird.body.y < 0.0) && !gameover{
-            play_sound(&hit, PlaySoundParams { looped: false, volume: 1.0 });
+        // The round ends when the last bird dies.
+        if !gameover && birds.iter().all(|bird| !bird.alive) {
             gameover = true;
-            collision_detected = true;
             running = false;
+            wallet.save();
+            // Versus rounds don't count towards records, and assisted runs
+            // don't unlock score skins, as they don't set the highest score.
+            match level_run.as_ref().map(|run| run.index) {
+                _ if players > 1 => {}
+                Some(Some(index)) => campaign.record(index, score, false),
+                Some(None) => {}
+                None if settings.assisted() => {}
+                None => skins.record(score),
+            }
         }
-        if pipes.len() >= 1 {
-            if pipes[pipes.len()-1].body_lower.x < screen_width() - rand::gen_range(300.0, 500.0) {
-                pipes.push(build_pipe(screen_width(), 0.0, screen_width()/10.0, rand::gen_range(100.0,screen_height() - 350.0)));
+        match &mut level_run {
+            Some(run) => {
+                if running {
+                    run.go(score as f32, scroll);
+                }
+                run.update(&mut pipes, &mut coins, ground.top(), gap_assist, screen_width());
+                if run.finished && !gameover {
+                    // Birds that make it to the finish line stay where they
+                    // are instead of dropping to the ground, and count as
+                    // landed so the next press restarts.
+                    for bird in birds.iter_mut().filter(|bird| bird.alive) {
+                        bird.landed = true;
+                    }
+                    particles.emit("sparkle", birds[focus].center());
+                    audio.play(Sfx::Point);
+                    if let (Some(index), 1) = (run.index, players) {
+                        campaign.record(index, score, true);
+                    }
+                    gameover = true;
+                    running = false;
+                    wallet.save();
+                }
+                run.draw(ground.top());
+            }
+            None => {
+                // Now and then a power-up takes a coin's place.
+                if let Some(coin) = course.update(&mut pipes, running, score, ground.top(), gap_assist, screen_width()) {
+                    if !powerups.roll(coin.pos, score) {
+                        coins.push(coin);
+                    }
+                }
             }
-        } else if running{
-            pipes.push(build_pipe(screen_width(), 0.0, screen_width()/10.0, rand::gen_range(100.0,screen_height() - 350.0)));
+        }
+        if gameover {
+            if let Some(mut replay) = recording.take() {
+                replay.score = birds[0].score;
+                replay.save(REPLAY_FILE);
+                if let Some(leaderboard) = &mut leaderboard {
+                    leaderboard.submit(&replay);
+                }
+            }
+        }
+        if let Some(leaderboard) = &mut leaderboard {
+            leaderboard.poll();
         }
         pipes.retain(|pipe| !pipe.edge());
 
+        if settings.high_contrast {
+            ground.draw_flat(palette.ground);
+        } else {
+            ground.draw(&assets.ground, sky.tint());
+        }
+
         //HANDLE BIRD
         if running ^ gameover {
-            if i%1 == 0 {
-                bird.fall();
+            for _ in 0..camera.steps() {
+                for bird in birds.iter_mut().filter(|bird| !bird.landed) {
+                    if i%1 == 0 {
+                        bird.fall(speed);
+                    }
+                    if bird.alive {
+                        if !seeded {
+                            bird.push(weather.wind() * speed);
+                        }
+                    } else {
+                        if bird.velocity > 3.0 && bird.body.y + bird.sprite_height() >= ground.top() {
+                            particles.emit("dust", vec2(bird.center().x, ground.top()));
+                            camera.add_trauma(0.3, &settings);
+                        }
+                        bird.landed = bird.land(ground.top());
+                    }
+                }
             }
-        } else {
-            draw_text("PRESS SPACE/LMB TO BEGIN", screen_width()/2.0 - 370.0, screen_height()/2.0 - 100.0, 70.0, WHITE);
         }
 
-        if (is_key_pressed(macroquad::input::KeyCode::Space) || is_mouse_button_pressed(MouseButton::Left)) && !pressed{
+        if !running && !gameover {
+            if !shop.open {
+                menu.update(&mut settings);
+            }
+            if !menu.open && shop.update(&mut skins, &campaign, &mut wallet) {
+                for bird in &birds {
+                    particles.emit("sparkle", bird.center());
+                }
+                if parallax.dir != skins.background().dir() {
+                    parallax = Parallax::load(&skins.background().dir()).await;
+                }
+                if let Some(net) = &mut net {
+                    net.skin = skins.current().id.clone();
+                }
+            }
+            if !menu.open && !shop.open && !lobby && is_key_pressed(KeyCode::Right) {
+                selected = campaign.cycle(selected, 1);
+                daily = false;
+            }
+            if !menu.open && !shop.open && !lobby && is_key_pressed(KeyCode::Left) {
+                selected = campaign.cycle(selected, -1);
+                daily = false;
+            }
+            if !menu.open && !shop.open && !lobby && is_key_pressed(KeyCode::D) {
+                daily ^= true;
+                selected = None;
+                if let (Some(leaderboard), true) = (&leaderboard, daily) {
+                    leaderboard.refresh(daily_seed());
+                }
+            }
+            if let (Some(net), false, false, true) = (&mut net, menu.open, shop.open, is_key_pressed(KeyCode::Enter)) {
+                net.go();
+            }
+            if !menu.open && !shop.open && !lobby && is_key_pressed(KeyCode::Key2) {
+                players = 3 - players;
+                birds = make_birds(players);
+                focus = 0;
+            }
+        }
+        if is_key_pressed(KeyCode::M) {
+            settings.mute ^= true;
+            settings.save();
+            audio.apply(&settings);
+        }
+        audio.set_ducked(gameover || menu.open || shop.open);
+        audio.set_music(if running { MusicState::Run } else { MusicState::Calm }, score);
+        audio.update(frame_time);
+
+        // Player 1's press is held back until Space is released.
+        let flaps: Vec<bool> = match &playback {
+            Some(replay) => vec![!gameover && replay.flaps.binary_search(&replay.ticks).is_ok()],
+            None => (0..birds.len()).map(|n| flap_pressed(n) && (n > 0 || !pressed)).collect(),
+        };
+        if flaps.contains(&true) && !menu.open && !shop.open && !lobby{
             if !gameover {
-                flappy_animation = 0;
-                bird.jump(); 
+                // Starting a run launches every bird; after that each
+                // flaps on its own button. With hold-to-hover the press
+                // only starts the run.
+                for (n, (bird, &flap)) in birds.iter_mut().zip(&flaps).enumerate() {
+                    if bird.alive && (!running || (flap && !hover)) {
+                        bird.flapped = 0;
+                        bird.jump(); 
+                        particles.emit("flap", bird.center());
+                        audio.play(Sfx::Flap);
+                        if let (Some(replay), 0, true) = (&mut recording, n, running) {
+                            replay.flap();
+                        }
+                        if let (Some(net), 0, true) = (&mut net, n, running) {
+                            net.flap();
+                        }
+                    }
+                }
+                if !running && daily {
+                    let seed = playback.as_ref().map_or_else(daily_seed, |replay| replay.seed);
+                    level_run = Some(LevelRun::new(Level::seeded(seed, ground.top(), screen_width()), None, -screen_width()));
+                    // Only runs the server can fly again the same way.
+                    let standard = screen_width() == replay::WIDTH && screen_height() == replay::HEIGHT && ground.top() == replay::FLOOR;
+                    if playback.is_none() && players == 1 && standard {
+                        recording = Some(Replay::new(seed));
+                    }
+                } else if !running {
+                    level_run = selected.map(|index| LevelRun::new(campaign.levels[index].clone(), Some(index), -screen_width()));
+                }
                 running = true;
-                play_sound(&flap, PlaySoundParams { looped: false, volume: 1.0 });
-            } else if bird.body.y > screen_height() {
+            } else if birds.iter().all(|bird| bird.landed) {
                 gameover = false;
-                collision_detected = false;
-                bird.die(&mut pipes, &gameover);
+                for bird in &mut birds {
+                    bird.die(&mut pipes, &gameover);
+                }
+                focus = 0;
+                coins.clear();
+                powerups.clear();
                 score = 0;
+                run_coins = 0;
+                sky.reset();
+                weather = Weather::new(settings.weather);
+                course.reset(settings.course);
+                level_run = None;
+                recording = None;
+                if let Some(leaderboard) = &mut leaderboard {
+                    leaderboard.status = None;
+                }
             }
             
-            pressed = true;
+            pressed |= flaps[0];
         } 
         if is_key_released(macroquad::input::KeyCode::Space) ||  is_mouse_button_pressed(MouseButton::Left){
             pressed = false;
         }
+        for (n, bird) in birds.iter_mut().enumerate() {
+            if hover && running && bird.alive && flap_down(n) {
+                bird.hover(speed);
+                bird.flapped = 0;
+            }
+        }
         
-        if flappy_animation < 20 {
-            draw_texture_ex(
-                &flappy_jump,
-                bird.body.x, 
-                bird.body.y,        
-                WHITE,
-                DrawTextureParams {
-                    dest_size: Some(vec2(bird.body.r, bird.body.r)),
-                    ..Default::default()
-                }
-            );
-        }else {
-            draw_texture_ex(
-                &flappy,
-                bird.body.x, 
-                bird.body.y,        
-                WHITE,
-                DrawTextureParams {
-                    dest_size: Some(vec2(bird.body.r, bird.body.r-10.0)),
-                    ..Default::default()
+        let (flappy, flappy_jump) = skins.current().textures(&assets);
+        // Races step on the same ticks as replays: after this frame's flaps.
+        if let (Some(net), Some(run), true) = (&mut net, &level_run, running || gameover) {
+            net.update(running);
+            net.draw(run.scroll(), &skins, &assets, &fonts);
+        }
+        for bird in &birds {
+            if running && bird.alive && tick.is_multiple_of(3) {
+                if let Some(trail) = &skins.current().trail {
+                    particles.emit(trail, bird.center());
                 }
-            );
+            }
+            if settings.high_contrast {
+                let center = bird.center();
+                draw_circle(center.x, center.y, bird.body.r/2.0, bird.tinted(palette.bird));
+                draw_circle_lines(center.x, center.y, bird.body.r/2.0, 4.0, palette.bird_outline);
+            } else if bird.flapped < 20 {
+                draw_texture_ex(
+                    flappy_jump,
+                    bird.body.x, 
+                    bird.body.y,        
+                    bird.tint,
+                    DrawTextureParams {
+                        dest_size: Some(vec2(bird.body.r, bird.body.r)),
+                        ..Default::default()
+                    }
+                );
+            }else {
+                draw_texture_ex(
+                    flappy,
+                    bird.body.x, 
+                    bird.body.y,        
+                    bird.tint,
+                    DrawTextureParams {
+                        dest_size: Some(vec2(bird.body.r, bird.sprite_height())),
+                        ..Default::default()
+                    }
+                );
+            }
         }
 
-        if score > highest_score {
-            highest_score = score;
+        particles.update(frame_time * camera.time_scale() * speed);
+        particles.draw();
+
+        set_screen_camera(target.as_ref());
+        camera.draw_flash();
+
+        // Runs with assists on are ranked separately. Levels keep their own
+        // best scores in the campaign.
+        let best = if settings.assisted() { &mut highest_assisted_score } else { &mut highest_score };
+        if score > *best && level_run.is_none() && players == 1 {
+            *best = score;
         }
 
         if !running && !gameover {
-            draw_text(format!("Highest score: {}", highest_score).as_str(), 30.0, 45.0, 50.0, WHITE);
+            fonts.draw(strings.get(if lobby { "race.begin" } else { "prompt.begin" }), &TextStyle {
+                size: 44.0,
+                color: palette.text,
+                align: Align::Center,
+                anchor: vec2(0.5, 0.5),
+                offset: vec2(0.0, -120.0),
+                outline: Some((3.0, palette.outline)),
+                max_width: Some(760.0),
+                ..Default::default()
+            });
+            fonts.draw(&format!("{}   {}", strings.get("prompt.accessibility"), strings.get("prompt.shop")), &TextStyle {
+                size: 22.0,
+                color: palette.text,
+                align: Align::Center,
+                anchor: vec2(0.5, 0.5),
+                offset: vec2(0.0, -70.0),
+                outline: Some((2.0, palette.outline)),
+                ..Default::default()
+            });
+            let mode = match (&net, selected) {
+                (Some(net), _) if net.id.is_none() => strings.get("race.connecting").replace("{server}", &net.server),
+                (Some(net), _) => strings.plural("race.lobby", net.remotes.len() as i64 + 1),
+                (None, Some(index)) => {
+                    let stars = campaign.progress(index).stars as usize;
+                    format!(
+                        "{} {}{}",
+                        strings.get("mode.level").replace("{n}", &strings.number(index as i64 + 1)).replace("{name}", &campaign.levels[index].name),
+                        "★".repeat(stars),
+                        "☆".repeat(3 - stars.min(3))
+                    )
+                }
+                (None, None) if daily => strings.get("mode.daily").to_owned(),
+                (None, None) => strings.get("mode.endless").to_owned(),
+            };
+            let mode = if players > 1 { format!("{} • {}", mode, strings.get("mode.versus")) } else { mode };
+            let mode = if lobby { mode } else { format!("< {} >", mode) };
+            fonts.draw(&mode, &TextStyle {
+                size: 30.0,
+                color: palette.text,
+                align: Align::Center,
+                anchor: vec2(0.5, 0.5),
+                offset: vec2(0.0, -10.0),
+                outline: Some((2.0, palette.outline)),
+                max_width: Some(760.0),
+                ..Default::default()
+            });
+            let hint = match &net {
+                Some(net) => {
+                    let mut names: Vec<(&u32, &str)> = net.remotes.iter().map(|(id, remote)| (id, remote.name.as_str())).collect();
+                    names.sort();
+                    names.iter().map(|(_, name)| *name).collect::<Vec<_>>().join(", ")
+                }
+                None => strings.get("mode.hint").to_owned(),
+            };
+            fonts.draw(&hint, &TextStyle {
+                size: 20.0,
+                color: palette.text,
+                align: Align::Center,
+                anchor: vec2(0.5, 0.5),
+                offset: vec2(0.0, 25.0),
+                outline: Some((2.0, palette.outline)),
+                ..Default::default()
+            });
+            let best = match selected {
+                Some(index) => strings.plural("score.highest", campaign.progress(index).best),
+                None if settings.assisted() => strings.plural("score.highest_assisted", highest_assisted_score),
+                None => strings.plural("score.highest", highest_score),
+            };
+            fonts.draw(&best, &TextStyle {
+                size: 32.0,
+                color: palette.text,
+                offset: vec2(30.0, 35.0),
+                shadow: Some((vec2(2.0, 2.0), Color::new(0.0, 0.0, 0.0, 0.6))),
+                max_width: Some(740.0),
+                ..Default::default()
+            });
+            fonts.draw(&strings.plural("coins.wallet", wallet.coins), &TextStyle {
+                size: 24.0,
+                color: palette.text,
+                offset: vec2(30.0, 72.0),
+                shadow: Some((vec2(2.0, 2.0), Color::new(0.0, 0.0, 0.0, 0.6))),
+                max_width: Some(740.0),
+                ..Default::default()
+            });
+            // Top five today and of all time, side by side.
+            if let (Some(leaderboard), true) = (&leaderboard, daily) {
+                for (column, (key, scores)) in [("board.daily", &leaderboard.daily), ("board.global", &leaderboard.global)].into_iter().enumerate() {
+                    let mut lines = vec![strings.get(key).to_owned()];
+                    lines.extend(scores.iter().take(5).enumerate().map(|(n, (name, score))| format!("{}. {}  {}", n + 1, name, score)));
+                    for (row, line) in lines.iter().enumerate() {
+                        fonts.draw(line, &TextStyle {
+                            size: if row == 0 { 22.0 } else { 20.0 },
+                            color: palette.text,
+                            align: Align::Center,
+                            anchor: vec2(0.5, 0.5),
+                            offset: vec2((column as f32 - 0.5) * 360.0, 75.0 + row as f32 * 26.0),
+                            outline: Some((2.0, palette.outline)),
+                            max_width: Some(340.0),
+                            ..Default::default()
+                        });
+                    }
+                }
+            }
+        }
+        if running || gameover {
+            Coin::new(vec2(40.0, 40.0) * ui_scale()).draw(0.0);
+            fonts.draw(&run_coins.to_string(), &TextStyle {
+                font: FontKind::Digits,
+                size: 32.0,
+                offset: vec2(62.0, 40.0),
+                color: palette.text,
+                outline: Some((3.0, palette.outline)),
+                ..Default::default()
+            });
         }
         if running && !gameover {
-            draw_text(format!("{}", score).as_str(), (screen_width() - measure_text(format!("{}", score).as_str(), None, 120, 1.0).width) / 2.0, (160.0) / 2.0, 120.0, WHITE);
-            //draw_text(format!("Current score: {}", score).as_str(), 30.0, 45.0, 50.0, WHITE);
+            for (n, bird) in birds.iter().enumerate() {
+                bird.powers.draw_hud(n, &fonts, &palette);
+            }
+            // Side by side in versus, each in its bird's tint.
+            for (n, bird) in birds.iter().enumerate() {
+                let x = (n as f32 - (birds.len() - 1) as f32 / 2.0) * 240.0;
+                fonts.draw(&bird.score.to_string(), &TextStyle {
+                    font: FontKind::Digits,
+                    size: 72.0,
+                    align: Align::Center,
+                    anchor: vec2(0.5, 0.0),
+                    offset: vec2(x, 70.0),
+                    color: if bird.alive { bird.tinted(palette.text) } else { Color::new(0.5, 0.5, 0.5, 1.0) },
+                    outline: Some((4.0, palette.outline)),
+                    ..Default::default()
+                });
+            }
         }
-        if gameover {
-            //sdraw_text(format!("{}", score).as_str(), (screen_width() - measure_text(format!("{}", score).as_str(), None, 650, 1.0).width) / 2.0, (screen_height() + 280.0) / 2.0, 650.0, BLACK);
-            draw_text(format!("{}", score).as_str(), (screen_width() - measure_text(format!("{}", score).as_str(), None, 600, 1.0).width) / 2.0, (screen_height() + 250.0) / 2.0, 600.0, WHITE);
-            //draw_text(format!("Current score: {}", score).as_str(), 30.0, 45.0, 50.0, WHITE);
+        // The readout changes from frame to frame, which would break golden
+        // images.
+        if watcher.is_some() && !fixed_clock {
+            fonts.draw(&strings.plural("debug.fps", get_fps() as i64), &TextStyle {
+                size: 16.0,
+                align: Align::Right,
+                anchor: vec2(1.0, 0.0),
+                offset: vec2(-10.0, 15.0),
+                ..Default::default()
+            });
+        }
+        if gameover && level_run.as_ref().is_some_and(|run| run.finished) {
+            fonts.draw(strings.get("level.complete"), &TextStyle {
+                size: 48.0,
+                color: palette.text,
+                align: Align::Center,
+                anchor: vec2(0.5, 0.5),
+                offset: vec2(0.0, -160.0),
+                outline: Some((3.0, palette.outline)),
+                max_width: Some(760.0),
+                ..Default::default()
+            });
+        }
+        if gameover && players > 1 {
+            // Highest score wins; on a tie, whoever stayed up longer.
+            let (winner, _) = birds.iter().enumerate().max_by_key(|&(n, bird)| (bird.score, n == focus)).unwrap();
+            fonts.draw(&strings.get("versus.wins").replace("{n}", &strings.number(winner as i64 + 1)), &TextStyle {
+                size: 48.0,
+                color: birds[winner].tinted(palette.text),
+                align: Align::Center,
+                anchor: vec2(0.5, 0.5),
+                offset: vec2(0.0, -160.0),
+                outline: Some((3.0, palette.outline)),
+                max_width: Some(760.0),
+                ..Default::default()
+            });
+            for (n, bird) in birds.iter().enumerate() {
+                fonts.draw(&bird.score.to_string(), &TextStyle {
+                    font: FontKind::Digits,
+                    size: 200.0,
+                    align: Align::Center,
+                    anchor: vec2(0.5, 0.5),
+                    offset: vec2((n as f32 - 0.5) * 360.0, 40.0),
+                    color: bird.tinted(palette.text),
+                    outline: Some((8.0, palette.outline)),
+                    ..Default::default()
+                });
+            }
+        } else if gameover {
+            fonts.draw(&score.to_string(), &TextStyle {
+                font: FontKind::Digits,
+                size: 300.0,
+                align: Align::Center,
+                anchor: vec2(0.5, 0.5),
+                offset: vec2(0.0, 40.0),
+                color: palette.text,
+                outline: Some((10.0, palette.outline)),
+                ..Default::default()
+            });
+        }
+        if let (Some(status), true) = (leaderboard.as_ref().and_then(|leaderboard| leaderboard.status), gameover) {
+            fonts.draw(strings.get(status.key()), &TextStyle {
+                size: 24.0,
+                color: palette.text,
+                align: Align::Center,
+                anchor: vec2(0.5, 0.5),
+                offset: vec2(0.0, 210.0),
+                outline: Some((2.0, palette.outline)),
+                max_width: Some(760.0),
+                ..Default::default()
+            });
+        }
+        if menu.open {
+            menu.draw(&settings, &fonts, &strings, &palette);
+        }
+        if shop.open {
+            shop.draw(&skins, &assets, &fonts, &strings, &palette);
         }
         
 
+        for bird in &mut birds {
+            bird.flapped = if running { bird.flapped + 1 } else { 100 };
+        }
         if running {
             i+=1;
-            flappy_animation += 1;
         } else {
             i = 1;
-            flappy_animation = 100;
+        }
+        if let (Some(replay), true) = (&mut recording, running) {
+            replay.ticks += 1;
+        }
+        if let (Some(replay), true) = (&mut playback, running) {
+            replay.ticks += 1;
         }
 
         //draw_text("GAME", screen_width()/2.0 - 175.0, 175.0, 200.0, WHITE);
         //draw_text("OVER", screen_width()/2.0 - 175.0,305.0, 200.0, WHITE);
-        
-        next_frame().await;
-    }
-}
 
-fn draw_scrolling_background(background: &Texture2D, scroll_speed: f32, offset: &mut f32) {
-    *offset += scroll_speed;
+        if is_key_pressed(SCREENSHOT_KEY) {
+            screenshot::save(&screenshot::capture());
+        }
+        if args.screenshot_at == Some(tick) {
+            let image = screenshot::capture();
+            match &args.golden {
+                Some(golden) => {
+                    if let Err(err) = screenshot::matches_golden(&image, golden).await {
+                        println!("golden image mismatch: {}", err);
+                        std::process::exit(1);
+                    }
+                    println!("matches {}", golden);
+                }
+                None => {
+                    if screenshot::save(&image).is_none() {
+                        std::process::exit(1);
+                    }
+                }
+            }
+            std::process::exit(0);
+        }
+        if let Some(export) = &mut export {
+            let mut written = if export.wants(tick) { export.push() } else { Ok(()) };
+            // The replay is over once the bird is down.
+            let done = gameover && birds.iter().all(|bird| bird.landed);
+            if done {
+                written = written.and_then(|_| export.finish());
+            }
+            if let Err(err) = written {
+                println!("failed to write export: {}", err);
+                std::process::exit(1);
+            }
+            if done {
+                std::process::exit(0);
+            }
+            export.show();
+        }
+        tick += 1;
 
-    if *offset >= background.width() {
-        *offset = 0.0;
+        next_frame().await;
     }
-
-    draw_texture_ex(
-        background,
-         -(*offset), 
-        0.0,        
-        WHITE,
-        DrawTextureParams {
-            dest_size: Some(vec2(background.width(), screen_height())),
-            ..Default::default()
-        }
-    );
-    draw_texture_ex(
-        background,
-        -(*offset) + background.width()-1.0, 
-        0.0,        
-        WHITE,
-        DrawTextureParams {
-            dest_size: Some(vec2(background.width(), screen_height())),
-            ..Default::default()
-        }
-    );
 }
 
 fn circle_rect_intersect(
@@ -0,0 +1,151 @@
+use macroquad::audio::{load_sound, play_sound, set_sound_volume, stop_sound, PlaySoundParams, Sound};
+
+use crate::assets::ASSET_DIR;
+
+pub const MUSIC_FILE: &str = "music.txt";
+
+// Level change per second when a stem fades in or out.
+const FADE_SPEED: f32 = 0.8;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MusicState {
+    // Start and game-over screens.
+    Calm,
+    Run,
+}
+
+struct Stem {
+    sound: Sound,
+    file: String,
+    gain: f32,
+    // Score at which a layer fades in. Unused for the calm track.
+    from_score: i64,
+    // Current fade level, 0 to 1.
+    level: f32,
+}
+
+impl Stem {
+    fn fade(&mut self, target: f32, dt: f32) {
+        let step = FADE_SPEED * dt;
+        self.level = if self.level < target { (self.level + step).min(target) } else { (self.level - step).max(target) };
+    }
+}
+
+// Stem-based music described by assets/music.txt. Layers add intensity as
+// the score rises during a run and the calm track crossfades in when the
+// run ends.
+pub struct Music {
+    layers: Vec<Stem>,
+    calm: Option<Stem>,
+    pub state: MusicState,
+    pub score: i64,
+    layers_playing: bool,
+    calm_playing: bool,
+}
+
+impl Music {
+    pub async fn load() -> Music {
+        let text = std::fs::read_to_string(format!("{}/{}", ASSET_DIR, MUSIC_FILE)).unwrap_or_else(|err| {
+            println!("failed to load {}: {}", MUSIC_FILE, err);
+            "layer music.ogg 0".to_owned()
+        });
+        let mut layers = vec![];
+        let mut calm = None;
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let parsed = match fields.as_slice() {
+                ["layer", file, score, rest @ ..] => score.parse().ok().zip(gain(rest)).map(|(score, gain)| (true, *file, score, gain)),
+                ["calm", file, rest @ ..] => gain(rest).map(|gain| (false, *file, 0, gain)),
+                _ => None,
+            };
+            let Some((is_layer, file, from_score, gain)) = parsed else {
+                println!("{}:{}: bad line: {}", MUSIC_FILE, n + 1, line);
+                continue;
+            };
+            let sound = match load_sound(&format!("{}/{}", ASSET_DIR, file)).await {
+                Ok(sound) => sound,
+                Err(err) => {
+                    println!("failed to load {}: {}", file, err);
+                    continue;
+                }
+            };
+            let stem = Stem { sound, file: file.to_owned(), gain, from_score, level: 0.0 };
+            if is_layer {
+                layers.push(stem);
+            } else {
+                calm = Some(stem);
+            }
+        }
+        Music { layers, calm, state: MusicState::Calm, score: 0, layers_playing: false, calm_playing: false }
+    }
+
+    pub fn uses(&self, name: &str) -> bool {
+        name == MUSIC_FILE || self.layers.iter().chain(&self.calm).any(|stem| stem.file == name)
+    }
+
+    pub fn stop(&mut self) {
+        for stem in self.layers.iter().chain(&self.calm) {
+            stop_sound(&stem.sound);
+        }
+        self.layers_playing = false;
+        self.calm_playing = false;
+    }
+
+    pub fn set_state(&mut self, state: MusicState, score: i64) {
+        self.state = state;
+        self.score = score;
+    }
+
+    // `volume` is the music bus level; it is applied every frame so bus
+    // changes and ducking take effect immediately.
+    pub fn update(&mut self, dt: f32, volume: f32) {
+        let run = self.state == MusicState::Run;
+
+        // Layers are started together, silent, so they stay in sync, and
+        // only stopped once the last of them has faded out.
+        if run && !self.layers_playing {
+            for stem in &mut self.layers {
+                stem.level = 0.0;
+                play_sound(&stem.sound, PlaySoundParams { looped: true, volume: 0.0 });
+            }
+            self.layers_playing = true;
+        }
+        for stem in &mut self.layers {
+            let target = if run && self.score >= stem.from_score { 1.0 } else { 0.0 };
+            stem.fade(target, dt);
+            set_sound_volume(&stem.sound, volume * stem.gain * stem.level);
+        }
+        if !run && self.layers_playing && self.layers.iter().all(|stem| stem.level == 0.0) {
+            for stem in &self.layers {
+                stop_sound(&stem.sound);
+            }
+            self.layers_playing = false;
+        }
+
+        if let Some(calm) = &mut self.calm {
+            let target = if run { 0.0 } else { 1.0 };
+            calm.fade(target, dt);
+            if target > 0.0 && !self.calm_playing {
+                play_sound(&calm.sound, PlaySoundParams { looped: true, volume: 0.0 });
+                self.calm_playing = true;
+            }
+            if target == 0.0 && calm.level == 0.0 && self.calm_playing {
+                stop_sound(&calm.sound);
+                self.calm_playing = false;
+            }
+            set_sound_volume(&calm.sound, volume * calm.gain * calm.level);
+        }
+    }
+}
+
+fn gain(rest: &[&str]) -> Option<f32> {
+    match rest {
+        [] => Some(1.0),
+        [gain] => gain.parse().ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::net::UdpSocket;
+
+use macroquad::prelude::*;
+
+use crate::assets::Assets;
+use crate::protocol::{Message, DEFAULT_PORT, MAX_PACKET};
+use crate::replay::Flight;
+use crate::skins::Skins;
+use crate::text::{ui_scale, Align, Fonts, TextStyle};
+use crate::{BIRD_SIZE, BIRD_X};
+
+// Seconds between keep-alives, which also retry a lost join.
+const KEEP_ALIVE: f64 = 2.0;
+
+// Ticks of flaps repeated in every input message, so that a few lost
+// packets in a row don't lose a flap.
+const FLAP_WINDOW: u64 = 120;
+
+// Remote birds are predicted at most this many ticks past their last
+// input; anything older is too stale to guess from.
+const MAX_PREDICT: u64 = 60;
+// Share of the distance to the predicted position covered each frame, so
+// corrections glide instead of snapping.
+const SMOOTHING: f32 = 0.3;
+
+// Someone else in the room.
+pub struct Remote {
+    pub name: String,
+    pub skin: String,
+    // Their flight up to the newest tick heard from them, when every flap
+    // before it is known.
+    known: Option<Flight>,
+    // Flaps heard that `known` hasn't reached yet.
+    flaps: Vec<u64>,
+    heard: u64,
+    // Where the bird is drawn: level x of the left screen edge it sees, and y.
+    shown: Option<(f32, f32)>,
+    alive: bool,
+}
+
+impl Remote {
+    fn new() -> Remote {
+        Remote { name: String::new(), skin: String::new(), known: None, flaps: vec![], heard: 0, shown: None, alive: true }
+    }
+
+    // Takes in their flaps up to `tick`.
+    fn hear(&mut self, tick: u64, flaps: Vec<u64>) {
+        // Packets can arrive out of order.
+        if tick <= self.heard {
+            return;
+        }
+        let known = self.known.as_ref().map_or(0, |known| known.tick);
+        for flap in flaps.into_iter().filter(|&flap| flap > known) {
+            if !self.flaps.contains(&flap) {
+                self.flaps.push(flap);
+            }
+        }
+        self.heard = tick;
+    }
+
+    // Flies the known part again with the flaps that came in, then guesses
+    // the rest up to `tick` as no flaps.
+    fn update(&mut self, tick: u64) {
+        let Some(known) = &mut self.known else {
+            return;
+        };
+        while known.flying && known.tick < self.heard {
+            let next = known.tick + 1;
+            known.step(self.flaps.contains(&next));
+        }
+        self.flaps.retain(|&flap| flap > known.tick);
+        let mut now = known.clone();
+        while now.flying && now.tick < tick.min(known.tick + MAX_PREDICT) {
+            now.step(false);
+        }
+        let target = (now.scroll(), now.bird.y);
+        self.shown = Some(match self.shown {
+            Some((scroll, y)) => (scroll + (target.0 - scroll) * SMOOTHING, y + (target.1 - y) * SMOOTHING),
+            None => target,
+        });
+        self.alive = !now.crashed;
+    }
+}
+
+// Connection to a race relay (see src/bin/relay.rs and protocol.rs).
+pub struct NetClient {
+    socket: UdpSocket,
+    pub server: String,
+    room: String,
+    name: String,
+    // Sent with keep-alives, so a new skin shows up for the others.
+    pub skin: String,
+    pub id: Option<u32>,
+    pub remotes: HashMap<u32, Remote>,
+    // Frames since the current race started.
+    tick: u64,
+    // This player's flaps in the race, as ticks.
+    flaps: Vec<u64>,
+    last_sent: f64,
+}
+
+impl NetClient {
+    // `server` is host:port, or just a host for the default port.
+    pub fn connect(server: &str, room: &str, name: &str, skin: &str) -> Option<NetClient> {
+        let server = if server.contains(':') { server.to_owned() } else { format!("{}:{}", server, DEFAULT_PORT) };
+        let socket = UdpSocket::bind("0.0.0.0:0").and_then(|socket| {
+            socket.connect(&server)?;
+            socket.set_nonblocking(true)?;
+            Ok(socket)
+        });
+        let socket = match socket {
+            Ok(socket) => socket,
+            Err(err) => {
+                println!("failed to reach relay {}: {}", server, err);
+                return None;
+            }
+        };
+        let client = NetClient {
+            socket,
+            server,
+            room: room.to_owned(),
+            name: name.to_owned(),
+            skin: skin.to_owned(),
+            id: None,
+            remotes: HashMap::new(),
+            tick: 0,
+            flaps: vec![],
+            last_sent: f64::MIN,
+        };
+        Some(client)
+    }
+
+    fn send(&mut self, message: Message) {
+        // Lost packets are fine; recent flaps are sent every frame and joins
+        // repeat.
+        let _ = self.socket.send(message.encode().as_bytes());
+        self.last_sent = get_time();
+    }
+
+    // Asks the relay to start a race for the room.
+    pub fn go(&mut self) {
+        self.send(Message::Go);
+    }
+
+    // Handles everything the relay sent since last frame. Returns the seed
+    // when a race starts and this player is in the `lobby` to join it;
+    // otherwise the race goes on without them.
+    pub fn poll(&mut self, lobby: bool) -> Option<u64> {
+        if get_time() - self.last_sent > KEEP_ALIVE {
+            self.send(Message::Join { room: self.room.clone(), name: self.name.clone(), skin: self.skin.clone() });
+        }
+        let mut race = None;
+        let mut buffer = [0; MAX_PACKET];
+        while let Ok(len) = self.socket.recv(&mut buffer) {
+            let Some(message) = Message::decode(&String::from_utf8_lossy(&buffer[..len])) else {
+                continue;
+            };
+            match message {
+                Message::Welcome { id } => self.id = Some(id),
+                Message::Lobby { players } => {
+                    self.remotes.retain(|id, _| players.iter().any(|player| player.id == *id));
+                    for player in players.into_iter().filter(|player| Some(player.id) != self.id) {
+                        let remote = self.remotes.entry(player.id).or_insert_with(Remote::new);
+                        remote.name = player.name;
+                        remote.skin = player.skin;
+                    }
+                }
+                Message::Race { .. } if !lobby => {}
+                Message::Race { seed } => {
+                    // The race starts with a flap, tick 0, before this
+                    // frame's physics, so this frame already plays tick 1.
+                    self.tick = 1;
+                    self.flaps.clear();
+                    for remote in self.remotes.values_mut() {
+                        remote.known = Some(Flight::new(seed));
+                        remote.flaps.clear();
+                        remote.heard = 0;
+                        remote.shown = None;
+                        remote.alive = true;
+                    }
+                    race = Some(seed);
+                }
+                Message::Player { id, tick, flaps } => {
+                    if let Some(remote) = self.remotes.get_mut(&id) {
+                        remote.hear(tick, flaps);
+                    }
+                }
+                Message::Left { id } => {
+                    self.remotes.remove(&id);
+                }
+                _ => {}
+            }
+        }
+        race
+    }
+
+    // The local player flapped this frame.
+    pub fn flap(&mut self) {
+        self.flaps.push(self.tick);
+    }
+
+    // Sends this frame's inputs while `racing`, moves the remote birds to
+    // this frame from theirs, and moves on to the next tick. Called once a
+    // frame after the flaps are read. The relay ends the race once nobody
+    // sends inputs any more.
+    pub fn update(&mut self, racing: bool) {
+        let tick = self.tick;
+        if racing {
+            let flaps = self.flaps.iter().copied().filter(|&flap| flap + FLAP_WINDOW > tick).collect();
+            self.send(Message::Input { tick, flaps });
+        }
+        for remote in self.remotes.values_mut() {
+            remote.update(tick);
+        }
+        self.tick += 1;
+    }
+
+    // Remote birds in their own skins, see-through and labelled, relative
+    // to the local `scroll`.
+    pub fn draw(&self, scroll: f32, skins: &Skins, assets: &Assets, fonts: &Fonts) {
+        let scale = ui_scale();
+        for remote in self.remotes.values() {
+            let Some((their_scroll, y)) = remote.shown else {
+                continue;
+            };
+            let x = BIRD_X + their_scroll - scroll;
+            let alpha = if remote.alive { 0.5 } else { 0.2 };
+            let (texture, _) = skins.find(&remote.skin).textures(assets);
+            draw_texture_ex(texture, x, y, Color::new(1.0, 1.0, 1.0, alpha), DrawTextureParams {
+                dest_size: Some(vec2(BIRD_SIZE, BIRD_SIZE - 10.0)),
+                ..Default::default()
+            });
+            fonts.draw(&remote.name, &TextStyle {
+                size: 16.0,
+                color: Color::new(1.0, 1.0, 1.0, alpha + 0.3),
+                align: Align::Center,
+                offset: vec2(x + BIRD_SIZE / 2.0, y - 8.0) / scale,
+                outline: Some((1.0, BLACK)),
+                ..Default::default()
+            });
+        }
+    }
+}
+
+impl Drop for NetClient {
+    fn drop(&mut self) {
+        self.send(Message::Leave);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::autopilot;
+
+    #[test]
+    fn remote_birds_fly_their_replay_from_late_inputs() {
+        let replay = autopilot(20261019);
+        let mut remote = Remote::new();
+        remote.known = Some(Flight::new(replay.seed));
+        // Inputs come in bursts, 10 ticks behind, and every other burst is lost.
+        let end = replay.flaps.last().unwrap() + 100;
+        for tick in 1..end {
+            if tick % 20 == 10 {
+                let heard = tick - 10;
+                remote.hear(heard, replay.flaps.iter().copied().filter(|&flap| flap <= heard && flap + FLAP_WINDOW > heard).collect());
+            }
+            remote.update(tick);
+        }
+        remote.hear(end, replay.flaps.clone());
+        remote.update(end);
+        let known = remote.known.unwrap();
+        assert!(!known.flying);
+        assert_eq!(known.score, replay.score);
+    }
+}
@@ -0,0 +1,213 @@
+use macroquad::prelude::*;
+
+use crate::assets::{texture, ASSET_DIR};
+
+pub const LAYER_FILE: &str = "parallax.txt";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tiling {
+    // Tiles at the texture's own width.
+    Repeat,
+    // One tile per screen width.
+    Stretch,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Anchor {
+    Fill,
+    Top,
+    Bottom,
+}
+
+pub struct Layer {
+    pub name: String,
+    pub texture: Texture2D,
+    // Fraction of the world (pipe) speed this layer scrolls at.
+    pub factor: f32,
+    pub tiling: Tiling,
+    pub anchor: Anchor,
+    pub height: Option<f32>,
+    offset: f32,
+}
+
+impl Layer {
+    // Whole pixels, so neighbouring tiles meet without a gap or overlap.
+    fn tile_width(&self) -> f32 {
+        match self.tiling {
+            Tiling::Repeat => self.texture.width(),
+            Tiling::Stretch => screen_width(),
+        }
+        .round()
+    }
+
+    fn rows(&self) -> (f32, f32) {
+        let h = self.height.unwrap_or(self.texture.height());
+        match self.anchor {
+            Anchor::Fill => (0.0, screen_height()),
+            Anchor::Top => (0.0, h),
+            Anchor::Bottom => (screen_height() - h, h),
+        }
+    }
+
+    fn update(&mut self, speed: f32) {
+        self.offset += speed * self.factor;
+        let w = self.tile_width();
+        if w > 0.0 {
+            self.offset = self.offset.rem_euclid(w);
+        }
+    }
+
+    fn draw(&self, tint: Color) {
+        let w = self.tile_width();
+        if w <= 1.0 {
+            return;
+        }
+        let (y, h) = self.rows();
+        let mut x = -self.offset.round();
+        while x < screen_width() {
+            draw_texture_ex(
+                &self.texture,
+                x,
+                y,
+                tint,
+                DrawTextureParams {
+                    dest_size: Some(vec2(w, h)),
+                    ..Default::default()
+                }
+            );
+            x += w;
+        }
+    }
+}
+
+// Background layers, drawn back to front in the order of the layer file.
+pub struct Parallax {
+    // Folder in assets the layer file and textures are in, empty for the
+    // top level.
+    pub dir: String,
+    pub layers: Vec<Layer>,
+}
+
+impl Parallax {
+    pub async fn load(dir: &str) -> Parallax {
+        let path = |name: &str| if dir.is_empty() { name.to_owned() } else { format!("{}/{}", dir, name) };
+        let specs = match std::fs::read_to_string(format!("{}/{}", ASSET_DIR, path(LAYER_FILE))) {
+            Ok(text) => parse(&text),
+            Err(_) => vec![],
+        };
+        // Without a layer file, the plain background from the top level.
+        let fallback = specs.is_empty();
+        let specs = if fallback { default_layers() } else { specs };
+
+        let mut layers = vec![];
+        for spec in specs {
+            // Layers go by their path from assets, like the watcher's names.
+            let name = if fallback { spec.name } else { path(&spec.name) };
+            match texture(&name).await {
+                Ok(texture) => layers.push(Layer {
+                    name,
+                    texture,
+                    factor: spec.factor,
+                    tiling: spec.tiling,
+                    anchor: spec.anchor,
+                    height: spec.height,
+                    offset: 0.0,
+                }),
+                Err(err) => println!("parallax: skipping {}: {}", name, err),
+            }
+        }
+        Parallax { dir: dir.to_owned(), layers }
+    }
+
+    // Rebuilds the layers when the layer file changes, or swaps one texture.
+    // Scroll offsets are kept for layers that survive the reload.
+    pub async fn reload(&mut self, name: &str) {
+        let file = if self.dir.is_empty() { LAYER_FILE.to_owned() } else { format!("{}/{}", self.dir, LAYER_FILE) };
+        if name == file {
+            let mut fresh = Parallax::load(&self.dir).await;
+            for layer in &mut fresh.layers {
+                if let Some(old) = self.layers.iter().find(|old| old.name == layer.name) {
+                    layer.offset = old.offset;
+                }
+            }
+            *self = fresh;
+            println!("reloaded {}", name);
+            return;
+        }
+        for layer in self.layers.iter_mut().filter(|layer| layer.name == name) {
+            match texture(name).await {
+                Ok(new) => layer.texture = new,
+                Err(err) => println!("failed to reload {}: {}", name, err),
+            }
+        }
+    }
+
+    pub fn update(&mut self, speed: f32) {
+        for layer in &mut self.layers {
+            layer.update(speed);
+        }
+    }
+
+    pub fn draw(&self, tint: Color) {
+        for layer in &self.layers {
+            layer.draw(tint);
+        }
+    }
+}
+
+struct LayerSpec {
+    name: String,
+    factor: f32,
+    tiling: Tiling,
+    anchor: Anchor,
+    height: Option<f32>,
+}
+
+fn default_layers() -> Vec<LayerSpec> {
+    vec![LayerSpec {
+        name: "background.png".to_owned(),
+        factor: 0.2,
+        tiling: Tiling::Repeat,
+        anchor: Anchor::Fill,
+        height: None,
+    }]
+}
+
+// One layer per line: `texture factor [repeat|stretch] [fill|top|bottom] [height]`.
+// Blank lines and lines starting with '#' are ignored.
+fn parse(text: &str) -> Vec<LayerSpec> {
+    let mut specs = vec![];
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_line(line) {
+            Some(spec) => specs.push(spec),
+            None => println!("{}:{}: bad layer line: {}", LAYER_FILE, n + 1, line),
+        }
+    }
+    specs
+}
+
+fn parse_line(line: &str) -> Option<LayerSpec> {
+    let mut fields = line.split_whitespace();
+    let name = fields.next()?.to_owned();
+    let factor = fields.next()?.parse().ok()?;
+    let tiling = match fields.next().unwrap_or("repeat") {
+        "repeat" => Tiling::Repeat,
+        "stretch" => Tiling::Stretch,
+        _ => return None,
+    };
+    let anchor = match fields.next().unwrap_or("fill") {
+        "fill" => Anchor::Fill,
+        "top" => Anchor::Top,
+        "bottom" => Anchor::Bottom,
+        _ => return None,
+    };
+    let height = match fields.next() {
+        Some(h) => Some(h.parse().ok()?),
+        None => None,
+    };
+    Some(LayerSpec { name, factor, tiling, anchor, height })
+}
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+
+use macroquad::prelude::*;
+
+use crate::assets::{texture, ASSET_DIR};
+
+pub const EMITTER_FILE: &str = "particles.txt";
+
+const POOL_SIZE: usize = 512;
+
+// How one burst of particles looks and moves. Ranges are (min, max) and
+// each particle picks its own value from them.
+#[derive(Clone)]
+pub struct EmitterDef {
+    pub count: usize,
+    pub lifetime: (f32, f32),
+    pub speed: (f32, f32),
+    // Degrees, 0 = right, 90 = down.
+    pub angle: (f32, f32),
+    pub spin: (f32, f32),
+    pub gravity: f32,
+    // Start and end size in pixels.
+    pub size: (f32, f32),
+    // Colors are spread evenly over the particle's lifetime.
+    pub colors: Vec<Color>,
+    pub texture: Option<String>,
+}
+
+impl Default for EmitterDef {
+    fn default() -> EmitterDef {
+        EmitterDef {
+            count: 10,
+            lifetime: (0.5, 1.0),
+            speed: (50.0, 150.0),
+            angle: (0.0, 360.0),
+            spin: (0.0, 0.0),
+            gravity: 0.0,
+            size: (6.0, 0.0),
+            colors: vec![WHITE],
+            texture: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Particle {
+    alive: bool,
+    emitter: usize,
+    pos: Vec2,
+    vel: Vec2,
+    rotation: f32,
+    spin: f32,
+    age: f32,
+    lifetime: f32,
+}
+
+impl Default for Particle {
+    fn default() -> Particle {
+        Particle {
+            alive: false,
+            emitter: 0,
+            pos: Vec2::ZERO,
+            vel: Vec2::ZERO,
+            rotation: 0.0,
+            spin: 0.0,
+            age: 0.0,
+            lifetime: 0.0,
+        }
+    }
+}
+
+// Fixed-size particle pool shared by all emitters. When it is full new
+// particles are dropped rather than growing the pool.
+pub struct Particles {
+    names: HashMap<String, usize>,
+    defs: Vec<EmitterDef>,
+    textures: HashMap<String, Texture2D>,
+    pool: Vec<Particle>,
+}
+
+impl Particles {
+    pub async fn load() -> Particles {
+        let text = std::fs::read_to_string(format!("{}/{}", ASSET_DIR, EMITTER_FILE)).unwrap_or_default();
+        let mut names = HashMap::new();
+        let mut defs = vec![];
+        for (name, def) in parse(&text) {
+            names.insert(name, defs.len());
+            defs.push(def);
+        }
+
+        let mut textures = HashMap::new();
+        for name in defs.iter().filter_map(|def| def.texture.clone()) {
+            match texture(&name).await {
+                Ok(tex) => {
+                    textures.insert(name, tex);
+                }
+                Err(err) => println!("particles: missing texture {}: {}", name, err),
+            }
+        }
+
+        Particles {
+            names,
+            defs,
+            textures,
+            pool: vec![Particle::default(); POOL_SIZE],
+        }
+    }
+
+    // Live particles keep flying with their old emitter index; if the new
+    // file has fewer emitters they are simply dropped.
+    pub async fn reload(&mut self, name: &str) {
+        if name == EMITTER_FILE || self.textures.contains_key(name) {
+            let fresh = Particles::load().await;
+            self.names = fresh.names;
+            self.defs = fresh.defs;
+            self.textures = fresh.textures;
+            for p in &mut self.pool {
+                p.alive &= p.emitter < self.defs.len();
+            }
+            println!("reloaded {}", name);
+        }
+    }
+
+    pub fn emit(&mut self, name: &str, at: Vec2) {
+        let Some(&emitter) = self.names.get(name) else {
+            return;
+        };
+        let def = &self.defs[emitter];
+        let mut free = self.pool.iter_mut().filter(|p| !p.alive);
+        for _ in 0..def.count {
+            let Some(p) = free.next() else {
+                return;
+            };
+            let angle = range(def.angle).to_radians();
+            *p = Particle {
+                alive: true,
+                emitter,
+                pos: at,
+                vel: vec2(angle.cos(), angle.sin()) * range(def.speed),
+                rotation: rand::gen_range(0.0, std::f32::consts::TAU),
+                spin: range(def.spin).to_radians(),
+                age: 0.0,
+                lifetime: range(def.lifetime),
+            };
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for p in self.pool.iter_mut().filter(|p| p.alive) {
+            let def = &self.defs[p.emitter];
+            p.age += dt;
+            if p.age >= p.lifetime {
+                p.alive = false;
+                continue;
+            }
+            p.vel.y += def.gravity * dt;
+            p.pos += p.vel * dt;
+            p.rotation += p.spin * dt;
+        }
+    }
+
+    pub fn draw(&self) {
+        for p in self.pool.iter().filter(|p| p.alive) {
+            let def = &self.defs[p.emitter];
+            let t = p.age / p.lifetime;
+            let size = def.size.0 + (def.size.1 - def.size.0) * t;
+            let color = ramp(&def.colors, t);
+
+            match def.texture.as_ref().and_then(|name| self.textures.get(name)) {
+                Some(tex) => draw_texture_ex(
+                    tex,
+                    p.pos.x - size / 2.0,
+                    p.pos.y - size / 2.0,
+                    color,
+                    DrawTextureParams {
+                        dest_size: Some(vec2(size, size)),
+                        rotation: p.rotation,
+                        ..Default::default()
+                    }
+                ),
+                None => draw_rectangle_ex(
+                    p.pos.x,
+                    p.pos.y,
+                    size,
+                    size / 2.0,
+                    DrawRectangleParams {
+                        offset: vec2(0.5, 0.5),
+                        rotation: p.rotation,
+                        color,
+                    }
+                ),
+            }
+        }
+    }
+}
+
+fn range((min, max): (f32, f32)) -> f32 {
+    if max > min { rand::gen_range(min, max) } else { min }
+}
+
+fn ramp(colors: &[Color], t: f32) -> Color {
+    match colors.len() {
+        0 => WHITE,
+        1 => colors[0],
+        n => {
+            let pos = t.clamp(0.0, 1.0) * (n - 1) as f32;
+            let i = (pos as usize).min(n - 2);
+            let f = pos - i as f32;
+            let (a, b) = (colors[i], colors[i + 1]);
+            Color::new(
+                a.r + (b.r - a.r) * f,
+                a.g + (b.g - a.g) * f,
+                a.b + (b.b - a.b) * f,
+                a.a + (b.a - a.a) * f,
+            )
+        }
+    }
+}
+
+// Emitters are `[name]` sections followed by `key value...` lines, e.g.
+//
+//   [flap]
+//   count 6
+//   lifetime 0.3 0.6
+//   colors #ffffff #ffffff00
+//
+// Keys that are left out keep their EmitterDef::default() value.
+fn parse(text: &str) -> Vec<(String, EmitterDef)> {
+    let mut emitters: Vec<(String, EmitterDef)> = vec![];
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            emitters.push((name.trim().to_owned(), EmitterDef::default()));
+            continue;
+        }
+        let ok = match emitters.last_mut() {
+            Some((_, def)) => parse_field(def, line).is_some(),
+            None => false,
+        };
+        if !ok {
+            println!("{}:{}: bad emitter line: {}", EMITTER_FILE, n + 1, line);
+        }
+    }
+    emitters
+}
+
+fn parse_field(def: &mut EmitterDef, line: &str) -> Option<()> {
+    let mut fields = line.split_whitespace();
+    let key = fields.next()?;
+    let values: Vec<&str> = fields.collect();
+    let pair = |values: &[&str]| -> Option<(f32, f32)> {
+        let min = values.first()?.parse().ok()?;
+        let max = match values.get(1) {
+            Some(v) => v.parse().ok()?,
+            None => min,
+        };
+        Some((min, max))
+    };
+    match key {
+        "count" => def.count = values.first()?.parse().ok()?,
+        "lifetime" => def.lifetime = pair(&values)?,
+        "speed" => def.speed = pair(&values)?,
+        "angle" => def.angle = pair(&values)?,
+        "spin" => def.spin = pair(&values)?,
+        "gravity" => def.gravity = values.first()?.parse().ok()?,
+        "size" => def.size = pair(&values)?,
+        "colors" => def.colors = values.iter().map(|v| parse_color(v)).collect::<Option<_>>()?,
+        "texture" => def.texture = values.first().map(|v| v.to_string()),
+        _ => return None,
+    }
+    Some(())
+}
+
+// #rrggbb or #rrggbbaa
+fn parse_color(hex: &str) -> Option<Color> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 && hex.len() != 8 {
+        return None;
+    }
+    let byte = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    let a = if hex.len() == 8 { byte(6)? } else { 255 };
+    Some(Color::from_rgba(byte(0)?, byte(2)?, byte(4)?, a))
+}
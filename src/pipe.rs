@@ -0,0 +1,312 @@
+use macroquad::prelude::*;
+
+use crate::assets::Assets;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PipeKind {
+    Static,
+    // Gap bobs up and down.
+    Oscillating,
+    // Gap narrows and widens.
+    Breathing,
+    // Gap slides slowly up or down, bouncing off the ends of its range.
+    Drifting,
+    // Two gaps with a solid section between them.
+    DoubleGap,
+}
+
+impl PipeKind {
+    pub fn parse(value: &str) -> Option<PipeKind> {
+        match value {
+            "static" => Some(PipeKind::Static),
+            "oscillating" => Some(PipeKind::Oscillating),
+            "breathing" => Some(PipeKind::Breathing),
+            "drifting" => Some(PipeKind::Drifting),
+            "double" => Some(PipeKind::DoubleGap),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PipeKind::Static => "static",
+            PipeKind::Oscillating => "oscillating",
+            PipeKind::Breathing => "breathing",
+            PipeKind::Drifting => "drifting",
+            PipeKind::DoubleGap => "double",
+        }
+    }
+}
+
+// Weight and the score from which each kind can spawn.
+const SPAWN_TABLE: [(PipeKind, f32, i64); 5] = [
+    (PipeKind::Static, 6.0, 0),
+    (PipeKind::Oscillating, 2.0, 10),
+    (PipeKind::Drifting, 2.0, 15),
+    (PipeKind::Breathing, 1.5, 20),
+    (PipeKind::DoubleGap, 1.0, 30),
+];
+
+// How a moving gap changes. Time is counted in frames scaled by the game
+// speed, like the rest of the physics.
+#[derive(Clone, Copy)]
+enum Motion {
+    None,
+    Oscillate { center: f32, amplitude: f32, gap: f32, t: f32 },
+    Breathe { center: f32, gap: f32, t: f32 },
+    Drift { center: f32, gap: f32, velocity: f32, min: f32, max: f32 },
+}
+
+#[derive(Clone)]
+pub struct Pipe {
+    pub body_upper: Rect,
+    pub body_lower: Rect,
+    // Only set for double-gap pipes.
+    pub body_middle: Option<Rect>,
+    // Bit n is set once bird n has flown past it.
+    pub scored: u8,
+    // Bit n is set once a power-up has let bird n through; it no longer
+    // collides with that bird.
+    pub passable: u8,
+    motion: Motion,
+}
+impl Pipe {
+    pub fn go(&mut self, score: f32, game_speed: f32) {
+        self.body_lower.x -= pipe_speed(score) * game_speed;
+        self.body_upper.x -= pipe_speed(score) * game_speed;
+        if let Some(middle) = &mut self.body_middle {
+            middle.x = self.body_upper.x;
+        }
+
+        let (center, gap) = match &mut self.motion {
+            Motion::None => return,
+            Motion::Oscillate { center, amplitude, gap, t } => {
+                *t += game_speed;
+                (*center + (*t * 0.04).sin() * *amplitude, *gap)
+            }
+            Motion::Breathe { center, gap, t } => {
+                *t += game_speed;
+                (*center, *gap * (0.825 + 0.175 * (*t * 0.05).cos()))
+            }
+            Motion::Drift { center, gap, velocity, min, max } => {
+                *center += *velocity * game_speed;
+                if *center < *min || *center > *max {
+                    *center = center.clamp(*min, *max);
+                    *velocity = -*velocity;
+                }
+                (*center, *gap)
+            }
+        };
+        self.layout(center, gap);
+    }
+    fn layout(&mut self, center: f32, gap: f32) {
+        self.body_upper.h = center - gap / 2.0;
+        self.body_lower.y = center + gap / 2.0;
+    }
+    pub fn edge(&self) -> bool {
+        self.body_upper.x < 0.0 - self.body_upper.w
+    }
+    // Everything the bird can hit, passable or not.
+    pub fn solids(&self) -> impl Iterator<Item = Rect> {
+        [self.body_upper, self.body_lower].into_iter().chain(self.body_middle)
+    }
+    // Top and bottom of each gap, from the top down.
+    pub fn gaps(&self) -> Vec<(f32, f32)> {
+        match self.body_middle {
+            Some(middle) => vec![(self.body_upper.bottom(), middle.top()), (middle.bottom(), self.body_lower.top())],
+            None => vec![(self.body_upper.bottom(), self.body_lower.top())],
+        }
+    }
+    pub fn moving(&self) -> bool {
+        !matches!(self.motion, Motion::None)
+    }
+    pub fn draw(&self, assets: &Assets) {
+        let tint = if self.passable != 0 { Color::new(1.0, 1.0, 1.0, 0.4) } else { WHITE };
+        draw_segment(self.body_upper, &assets.pipe_upper, &assets.pipe_body, false, tint);
+        draw_segment(self.body_lower, &assets.pipe_lower, &assets.pipe_body, true, tint);
+        if let Some(middle) = self.body_middle {
+            // Two halves so both ends get a cap.
+            let half = Rect { h: middle.h / 2.0, ..middle };
+            draw_segment(half, &assets.pipe_lower, &assets.pipe_body, true, tint);
+            draw_segment(Rect { y: middle.y + half.h, ..half }, &assets.pipe_upper, &assets.pipe_body, false, tint);
+        }
+    }
+    // High-contrast mode: plain rects with a thick outline.
+    pub fn draw_flat(&self, fill: Color, outline: Color) {
+        let (fill, outline) = if self.passable != 0 { (Color { a: 0.4, ..fill }, Color { a: 0.4, ..outline }) } else { (fill, outline) };
+        for rect in self.solids() {
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, fill);
+            draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 8.0, outline);
+        }
+    }
+}
+pub fn pipe_speed(score: f32) -> f32 {
+    5.0 + score / 5.0
+}
+
+// Narrowest and widest gap at gap_scale 1.0. Course uses MAX_GAP to keep
+// gaps above the ground.
+pub const MIN_GAP: f32 = 120.0;
+pub const MAX_GAP: f32 = 250.0;
+
+// Room kept free above the top and below the bottom gap.
+pub const MARGIN: f32 = 60.0;
+
+// Lower pipes run well past the bottom of the screen; draw_segment clips
+// them.
+const LOWER_LENGTH: f32 = 4096.0;
+
+// Picks a kind from SPAWN_TABLE for the current score and builds it.
+pub fn spawn_pipe(score: i64, x: f32, w: f32, center: f32, floor: f32, gap_scale: f32) -> Pipe {
+    let unlocked = SPAWN_TABLE.iter().filter(|(_, _, from)| score >= *from);
+    let total: f32 = unlocked.clone().map(|(_, weight, _)| weight).sum();
+    let mut roll = rand::gen_range(0.0, total);
+    let mut kind = PipeKind::Static;
+    for &(candidate, weight, _) in unlocked {
+        kind = candidate;
+        if roll < weight {
+            break;
+        }
+        roll -= weight;
+    }
+    build_pipe(kind, x, w, center, None, floor, gap_scale)
+}
+
+// Builds a pipe with its gap around `center`, moved as little as needed to
+// keep the whole gap (and its motion) between the top of the screen and
+// `floor`. For double-gap pipes `center` is one of the two gaps. `gap` sets
+// the gap size (before gap_scale) instead of picking one for the kind.
+pub fn build_pipe(kind: PipeKind, x: f32, w: f32, center: f32, gap: Option<f32>, floor: f32, gap_scale: f32) -> Pipe {
+    try_build(kind, x, w, center, gap, floor, gap_scale)
+        .or_else(|| try_build(PipeKind::Static, x, w, center, gap, floor, gap_scale))
+        .unwrap_or_else(|| Pipe {
+            body_upper: Rect::new(x, 0.0, w, 0.0),
+            body_lower: Rect::new(x, floor, w, LOWER_LENGTH),
+            body_middle: None,
+            scored: 0,
+            passable: 0,
+            motion: Motion::None,
+        })
+}
+
+// None if the kind doesn't fit on this screen.
+fn try_build(kind: PipeKind, x: f32, w: f32, center: f32, gap: Option<f32>, floor: f32, gap_scale: f32) -> Option<Pipe> {
+    let gap_size = |min: f32, max: f32| gap.unwrap_or_else(|| rand::gen_range(min, max)) * gap_scale;
+    // `center` clamped so that a gap of `gap`, moved by up to `reach`
+    // either way, stays clear of the margins.
+    let fit = |gap: f32, reach: f32| {
+        let (min, max) = (MARGIN + gap / 2.0 + reach, floor - MARGIN - gap / 2.0 - reach);
+        if min <= max { Some(center.clamp(min, max)) } else { None }
+    };
+    let motion = match kind {
+        PipeKind::Static => Motion::None,
+        PipeKind::Oscillating => {
+            let gap = gap_size(160.0, MAX_GAP);
+            let amplitude = rand::gen_range(40.0, 90.0);
+            Motion::Oscillate { center: fit(gap, amplitude)?, amplitude, gap, t: rand::gen_range(0.0, 150.0) }
+        }
+        PipeKind::Breathing => {
+            // Closes to 65% of this, which is still wider than the
+            // narrowest static gap.
+            let gap = gap_size(190.0, MAX_GAP);
+            Motion::Breathe { center: fit(gap, 0.0)?, gap, t: 0.0 }
+        }
+        PipeKind::Drifting => {
+            let gap = gap_size(160.0, MAX_GAP);
+            let center = fit(gap, 0.0)?;
+            let range = rand::gen_range(60.0, 140.0);
+            let speed = rand::gen_range(0.4, 0.9);
+            let velocity = if rand::gen_range(0, 2) == 0 { speed } else { -speed };
+            let (min, max) = ((center - range).max(MARGIN + gap / 2.0), (center + range).min(floor - MARGIN - gap / 2.0));
+            Motion::Drift { center, gap, velocity, min, max }
+        }
+        PipeKind::DoubleGap => {
+            let gap = gap_size(150.0, 190.0);
+            // Tall enough for a cap at each end.
+            let middle = 2.0 * w + rand::gen_range(20.0, 80.0);
+            // The other gap goes above or below, whichever has room.
+            let below = rand::gen_range(0, 2) == 0;
+            let top = if below { center - gap / 2.0 } else { center - 1.5 * gap - middle };
+            let top = if top < MARGIN || top + 2.0 * gap + middle > floor - MARGIN {
+                if below { center - 1.5 * gap - middle } else { center - gap / 2.0 }
+            } else {
+                top
+            };
+            if top < MARGIN || top + 2.0 * gap + middle > floor - MARGIN {
+                return None;
+            }
+            return Some(Pipe {
+                body_upper: Rect::new(x, 0.0, w, top),
+                body_middle: Some(Rect::new(x, top + gap, w, middle)),
+                body_lower: Rect::new(x, top + 2.0 * gap + middle, w, LOWER_LENGTH),
+                scored: 0,
+                passable: 0,
+                motion: Motion::None,
+            });
+        }
+    };
+    let mut pipe = Pipe {
+        body_upper: Rect::new(x, 0.0, w, 0.0),
+        body_lower: Rect::new(x, 0.0, w, LOWER_LENGTH),
+        body_middle: None,
+        scored: 0,
+        passable: 0,
+        motion,
+    };
+    if kind == PipeKind::Static {
+        let gap = gap_size(MIN_GAP, MAX_GAP);
+        pipe.layout(fit(gap, 0.0)?, gap);
+    } else {
+        // Lays the rects out around the starting gap.
+        pipe.go(0.0, 0.0);
+    }
+    Some(pipe)
+}
+
+// Draws one half of a pipe as two quads: the cap (a w*w square on the gap
+// side) and the body, which repeats pipe_body.png every w pixels along the
+// rest of the rect. Rects may start or end offscreen; the body is clipped to
+// the screen so the quad never gets absurdly tall.
+fn draw_segment(rect: Rect, cap: &Texture2D, body: &Texture2D, cap_at_top: bool, tint: Color) {
+    let cap_h = rect.w;
+    let (cap_y, body_top, body_bottom) = if cap_at_top {
+        (rect.y, rect.y + cap_h, rect.y + rect.h)
+    } else {
+        (rect.y + rect.h - cap_h, rect.y, rect.y + rect.h - cap_h)
+    };
+
+    let top = body_top.max(0.0);
+    let bottom = body_bottom.min(screen_height());
+    if bottom > top && rect.w > 0.0 {
+        // Measure tiles from the cap so the seam pattern doesn't crawl when clipped.
+        let (from, to) = if cap_at_top {
+            (top - body_top, bottom - body_top)
+        } else {
+            (body_bottom - bottom, body_bottom - top)
+        };
+        let tile = body.height() / rect.w;
+        draw_texture_ex(
+            body,
+            rect.x,
+            top,
+            tint,
+            DrawTextureParams {
+                dest_size: Some(vec2(rect.w, bottom - top)),
+                source: Some(Rect::new(0.0, from * tile, body.width(), (to - from) * tile)),
+                flip_y: !cap_at_top,
+                ..Default::default()
+            }
+        );
+    }
+
+    draw_texture_ex(
+        cap,
+        rect.x,
+        cap_y,
+        tint,
+        DrawTextureParams {
+            dest_size: Some(vec2(rect.w, cap_h)),
+            ..Default::default()
+        }
+    );
+}
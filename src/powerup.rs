@@ -0,0 +1,342 @@
+use macroquad::prelude::*;
+
+use crate::accessibility::Palette;
+use crate::pipe::pipe_speed;
+use crate::text::{ui_scale, Align, Fonts, TextStyle};
+
+// Share of coin spots that get a power-up instead, once the score is at
+// least MIN_SCORE.
+const CHANCE: f32 = 0.15;
+const MIN_SCORE: i64 = 5;
+
+const PICKUP_RADIUS: f32 = 18.0;
+
+// What a bird's active power-ups change this frame. Each starts at "no
+// change" and is folded over every active power-up.
+#[derive(Clone, Copy)]
+pub struct Effects {
+    // Size of the bird's collider and sprite.
+    pub bird_scale: f32,
+    // Multiplies the speed of the pipes and everything scrolling with them.
+    pub world_speed: f32,
+    // Coins within this many pixels fly to the bird.
+    pub magnet: f32,
+}
+
+impl Default for Effects {
+    fn default() -> Effects {
+        Effects { bird_scale: 1.0, world_speed: 1.0, magnet: 0.0 }
+    }
+}
+
+// What the bird was about to die from.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Hit {
+    Pipe,
+    Ground,
+    Ceiling,
+}
+
+// What picking up a power-up that is already active does.
+#[derive(Clone, Copy)]
+pub enum Stacking {
+    // Restarts the timer.
+    Refresh,
+    // Adds the duration to what's left, up to `max` seconds.
+    Extend { max: f32 },
+    // Adds a charge, up to `max`, and restarts the timer.
+    Charges { max: u32 },
+}
+
+// One kind of power-up. Implementations are stateless; timers and charges
+// are kept per bird by Powers. Adding a new one only takes an implementation and
+// an entry in POWER_UPS.
+pub trait PowerUp: Sync {
+    // Tells kinds apart when stacking.
+    fn name(&self) -> &'static str;
+    // Drawn on the pickup and in the HUD.
+    fn icon(&self) -> &'static str;
+    fn color(&self) -> Color;
+    // Seconds it lasts.
+    fn duration(&self) -> f32;
+    fn stacking(&self) -> Stacking {
+        Stacking::Refresh
+    }
+    fn apply(&self, _effects: &mut Effects) {}
+    // Whether it saves the bird from `hit`, using up a charge.
+    fn absorbs(&self, _hit: Hit) -> bool {
+        false
+    }
+}
+
+// Survives one hit of any kind.
+pub struct Shield;
+
+impl PowerUp for Shield {
+    fn name(&self) -> &'static str {
+        "shield"
+    }
+    fn icon(&self) -> &'static str {
+        "♥"
+    }
+    fn color(&self) -> Color {
+        Color::from_hex(0x4fc3f7)
+    }
+    fn duration(&self) -> f32 {
+        20.0
+    }
+    fn stacking(&self) -> Stacking {
+        Stacking::Charges { max: 3 }
+    }
+    fn absorbs(&self, _hit: Hit) -> bool {
+        true
+    }
+}
+
+pub struct Shrink;
+
+impl PowerUp for Shrink {
+    fn name(&self) -> &'static str {
+        "shrink"
+    }
+    fn icon(&self) -> &'static str {
+        "•"
+    }
+    fn color(&self) -> Color {
+        Color::from_hex(0x81c784)
+    }
+    fn duration(&self) -> f32 {
+        8.0
+    }
+    fn apply(&self, effects: &mut Effects) {
+        effects.bird_scale *= 0.6;
+    }
+}
+
+pub struct SlowMotion;
+
+impl PowerUp for SlowMotion {
+    fn name(&self) -> &'static str {
+        "slow_motion"
+    }
+    fn icon(&self) -> &'static str {
+        "◴"
+    }
+    fn color(&self) -> Color {
+        Color::from_hex(0xba68c8)
+    }
+    fn duration(&self) -> f32 {
+        6.0
+    }
+    fn stacking(&self) -> Stacking {
+        Stacking::Extend { max: 12.0 }
+    }
+    fn apply(&self, effects: &mut Effects) {
+        effects.world_speed *= 0.6;
+    }
+}
+
+pub struct Magnet;
+
+impl PowerUp for Magnet {
+    fn name(&self) -> &'static str {
+        "magnet"
+    }
+    fn icon(&self) -> &'static str {
+        "∪"
+    }
+    fn color(&self) -> Color {
+        Color::from_hex(0xe57373)
+    }
+    fn duration(&self) -> f32 {
+        10.0
+    }
+    fn apply(&self, effects: &mut Effects) {
+        effects.magnet = effects.magnet.max(180.0);
+    }
+}
+
+// Passes through one pipe.
+pub struct Ghost;
+
+impl PowerUp for Ghost {
+    fn name(&self) -> &'static str {
+        "ghost"
+    }
+    fn icon(&self) -> &'static str {
+        "◌"
+    }
+    fn color(&self) -> Color {
+        Color::from_hex(0xeeeeee)
+    }
+    fn duration(&self) -> f32 {
+        8.0
+    }
+    fn absorbs(&self, hit: Hit) -> bool {
+        hit == Hit::Pipe
+    }
+}
+
+// Every power-up that can spawn, equally likely.
+pub const POWER_UPS: [&dyn PowerUp; 5] = [&Shield, &Shrink, &SlowMotion, &Magnet, &Ghost];
+
+struct Active {
+    power: &'static dyn PowerUp,
+    // Seconds left.
+    left: f32,
+    charges: u32,
+}
+
+struct Pickup {
+    pos: Vec2,
+    power: &'static dyn PowerUp,
+}
+
+// Power-ups waiting to be picked up, shared by every bird in the course.
+pub struct PowerUps {
+    pickups: Vec<Pickup>,
+}
+
+impl PowerUps {
+    pub fn new() -> PowerUps {
+        PowerUps { pickups: vec![] }
+    }
+
+    pub fn clear(&mut self) {
+        self.pickups.clear();
+    }
+
+    // Sometimes puts a power-up at a spot picked for a coin, which is known
+    // to be reachable. Returns true if it took the spot.
+    pub fn roll(&mut self, pos: Vec2, score: i64) -> bool {
+        if score < MIN_SCORE || rand::gen_range(0.0, 1.0) >= CHANCE {
+            return false;
+        }
+        let power = POWER_UPS[rand::gen_range(0, POWER_UPS.len())];
+        self.pickups.push(Pickup { pos, power });
+        true
+    }
+
+    // Scrolls the pickups with the pipes.
+    pub fn go(&mut self, score: f32, game_speed: f32) {
+        for pickup in &mut self.pickups {
+            pickup.pos.x -= pipe_speed(score) * game_speed;
+        }
+        self.pickups.retain(|pickup| pickup.pos.x > -PICKUP_RADIUS);
+    }
+
+    // Takes whatever the bird touches, for that bird's Powers. Returns it
+    // with where it was picked up.
+    pub fn collect(&mut self, center: Vec2, radius: f32) -> Option<(&'static dyn PowerUp, Vec2)> {
+        let index = self.pickups.iter().position(|pickup| pickup.pos.distance(center) < radius + PICKUP_RADIUS)?;
+        let pickup = self.pickups.remove(index);
+        Some((pickup.power, pickup.pos))
+    }
+
+    // The pickups, in world space.
+    pub fn draw(&self, fonts: &Fonts, time: f32) {
+        let scale = ui_scale();
+        for pickup in &self.pickups {
+            let radius = PICKUP_RADIUS * (1.0 + 0.08 * (time * 6.0).sin());
+            draw_circle(pickup.pos.x, pickup.pos.y, radius, pickup.power.color());
+            draw_circle_lines(pickup.pos.x, pickup.pos.y, radius, 3.0, WHITE);
+            fonts.draw(pickup.power.icon(), &TextStyle {
+                size: 24.0,
+                color: BLACK,
+                align: Align::Center,
+                offset: pickup.pos / scale,
+                ..Default::default()
+            });
+        }
+    }
+}
+
+// The power-ups in effect on one bird.
+pub struct Powers {
+    active: Vec<Active>,
+}
+
+impl Powers {
+    pub fn new() -> Powers {
+        Powers { active: vec![] }
+    }
+
+    pub fn add(&mut self, power: &'static dyn PowerUp) {
+        let Some(active) = self.active.iter_mut().find(|active| active.power.name() == power.name()) else {
+            self.active.push(Active { power, left: power.duration(), charges: 1 });
+            return;
+        };
+        match power.stacking() {
+            Stacking::Refresh => active.left = power.duration(),
+            Stacking::Extend { max } => active.left = (active.left + power.duration()).min(max),
+            Stacking::Charges { max } => {
+                active.charges = (active.charges + 1).min(max);
+                active.left = power.duration();
+            }
+        }
+    }
+
+    // Runs the timers down.
+    pub fn update(&mut self, dt: f32) {
+        for active in &mut self.active {
+            active.left -= dt;
+        }
+        self.active.retain(|active| active.left > 0.0 && active.charges > 0);
+    }
+
+    pub fn effects(&self) -> Effects {
+        let mut effects = Effects::default();
+        for active in &self.active {
+            active.power.apply(&mut effects);
+        }
+        effects
+    }
+
+    // Whether a power-up saves the bird from `hit`. The one closest to
+    // running out is used first.
+    pub fn absorb(&mut self, hit: Hit) -> bool {
+        let Some(active) = self
+            .active
+            .iter_mut()
+            .filter(|active| active.power.absorbs(hit))
+            .min_by(|a, b| a.left.total_cmp(&b.left))
+        else {
+            return false;
+        };
+        active.charges -= 1;
+        self.active.retain(|active| active.charges > 0);
+        true
+    }
+
+    // A row of icons with the time left under each, top right. Player n
+    // gets row n.
+    pub fn draw_hud(&self, row: usize, fonts: &Fonts, palette: &Palette) {
+        let scale = ui_scale();
+        for (n, active) in self.active.iter().enumerate() {
+            // Virtual pixels from the top right corner.
+            let offset = vec2(-40.0 - n as f32 * 56.0, 70.0 + row as f32 * 64.0);
+            let pos = vec2(screen_width(), 0.0) + offset * scale;
+            draw_circle(pos.x, pos.y, 20.0 * scale, active.power.color());
+            draw_circle_lines(pos.x, pos.y, 20.0 * scale, 2.0 * scale, palette.outline);
+            let text = TextStyle { anchor: vec2(1.0, 0.0), offset, ..Default::default() };
+            fonts.draw(active.power.icon(), &TextStyle { size: 24.0, color: BLACK, align: Align::Center, ..text });
+
+            let full = match active.power.stacking() {
+                Stacking::Extend { max } => max,
+                _ => active.power.duration(),
+            };
+            let (w, h, y) = (40.0 * scale, 5.0 * scale, pos.y + 26.0 * scale);
+            draw_rectangle(pos.x - w / 2.0, y, w, h, Color::new(0.0, 0.0, 0.0, 0.5));
+            draw_rectangle(pos.x - w / 2.0, y, w * (active.left / full).clamp(0.0, 1.0), h, palette.text);
+            if active.charges > 1 {
+                fonts.draw(&format!("×{}", active.charges), &TextStyle {
+                    size: 16.0,
+                    color: palette.text,
+                    offset: offset + vec2(12.0, 14.0),
+                    outline: Some((2.0, palette.outline)),
+                    ..text
+                });
+            }
+        }
+    }
+}
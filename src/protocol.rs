@@ -0,0 +1,103 @@
+// Messages between the game and the race relay (src/bin/relay.rs). Each
+// UDP datagram is one line of text:
+//
+//   game -> relay              relay -> game
+//   join <room> <name> <skin>  welcome <id>
+//   go                         lobby [<id> <name> <skin> ...]
+//   input <tick> [<flap> ...]  race <seed>
+//   leave                      player <id> <tick> [<flap> ...]
+//                              left <id>
+//
+// `join` doubles as a keep-alive. Ticks count frames from the start of a
+// race, which is tick 0. Racers only send their inputs: every frame until
+// their bird is out, the ticks they flapped on over the last two seconds or
+// so, so a lost packet doesn't lose a flap. A room has one race at a time;
+// it ends once no racer has sent inputs for a while. Each game flies the others from those on its own
+// copy of the seeded course (see Flight in replay.rs).
+//
+// Shared with the relay binary, so std only.
+
+pub const DEFAULT_PORT: u16 = 7777;
+pub const MAX_PACKET: usize = 1024;
+// Longest room, name or skin in bytes, so that a full room's lobby message
+// still fits in one packet (see ROOM_SIZE in the relay).
+pub const MAX_WORD: usize = 16;
+
+// Someone in a room, as listed in a lobby message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Member {
+    pub id: u32,
+    pub name: String,
+    // Folder name of the skin they wear, see skins.rs.
+    pub skin: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    Join { room: String, name: String, skin: String },
+    // Asks the relay to start a race for the whole room.
+    Go,
+    // All flaps up to `tick` within the window, oldest first.
+    Input { tick: u64, flaps: Vec<u64> },
+    Leave,
+    Welcome { id: u32 },
+    Lobby { players: Vec<Member> },
+    Race { seed: u64 },
+    Player { id: u32, tick: u64, flaps: Vec<u64> },
+    Left { id: u32 },
+}
+
+impl Message {
+    pub fn encode(&self) -> String {
+        let ticks = |flaps: &[u64]| flaps.iter().map(|flap| format!(" {}", flap)).collect::<String>();
+        match self {
+            Message::Join { room, name, skin } => format!("join {} {} {}", word(room), word(name), word(skin)),
+            Message::Go => "go".to_owned(),
+            Message::Input { tick, flaps } => format!("input {}{}", tick, ticks(flaps)),
+            Message::Leave => "leave".to_owned(),
+            Message::Welcome { id } => format!("welcome {}", id),
+            Message::Lobby { players } => {
+                let mut text = "lobby".to_owned();
+                for player in players {
+                    text += &format!(" {} {} {}", player.id, word(&player.name), word(&player.skin));
+                }
+                text
+            }
+            Message::Race { seed } => format!("race {}", seed),
+            Message::Player { id, tick, flaps } => format!("player {} {}{}", id, tick, ticks(flaps)),
+            Message::Left { id } => format!("left {}", id),
+        }
+    }
+
+    pub fn decode(text: &str) -> Option<Message> {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        let ticks = |fields: &[&str]| fields.iter().map(|flap| flap.parse().ok()).collect::<Option<Vec<u64>>>();
+        Some(match fields.as_slice() {
+            ["join", room, name, skin] => Message::Join { room: room.to_string(), name: name.to_string(), skin: skin.to_string() },
+            ["go"] => Message::Go,
+            ["input", tick, rest @ ..] => Message::Input { tick: tick.parse().ok()?, flaps: ticks(rest)? },
+            ["leave"] => Message::Leave,
+            ["welcome", id] => Message::Welcome { id: id.parse().ok()? },
+            ["lobby", rest @ ..] if rest.len() % 3 == 0 => Message::Lobby {
+                players: rest
+                    .chunks(3)
+                    .map(|member| Some(Member { id: member[0].parse().ok()?, name: member[1].to_string(), skin: member[2].to_string() }))
+                    .collect::<Option<_>>()?,
+            },
+            ["race", seed] => Message::Race { seed: seed.parse().ok()? },
+            ["player", id, tick, rest @ ..] => Message::Player { id: id.parse().ok()?, tick: tick.parse().ok()?, flaps: ticks(rest)? },
+            ["left", id] => Message::Left { id: id.parse().ok()? },
+            _ => return None,
+        })
+    }
+}
+
+// Names and rooms travel as single words of at most MAX_WORD bytes.
+fn word(text: &str) -> String {
+    let mut word: String = text.split_whitespace().collect::<Vec<_>>().join("_");
+    if word.len() > MAX_WORD {
+        let end = (0..=MAX_WORD).rev().find(|&end| word.is_char_boundary(end)).unwrap_or(0);
+        word.truncate(end);
+    }
+    if word.is_empty() { "_".to_owned() } else { word }
+}
@@ -0,0 +1,214 @@
+use macroquad::prelude::*;
+
+use crate::coin::COIN_RADIUS;
+use crate::pipe::{pipe_speed, Pipe};
+use crate::{BIRD_SIZE, BIRD_X, GRAVITY, JUMP_VELOCITY};
+
+// The bird collides as a circle of this radius around its center.
+const RADIUS: f32 = BIRD_SIZE / 2.0;
+const BIRD_CENTER_X: f32 = BIRD_X + RADIUS;
+
+// How far above or below a coin the bird's center may pass and still be
+// counted as collecting it; less than the real pickup distance.
+const COIN_REACH: f32 = RADIUS / 2.0 + COIN_RADIUS;
+
+// Gives up on pipes that never reach the bird.
+const MAX_FRAMES: u32 = 5000;
+
+// The bird may enter the first gap on its way down, having glided for up
+// to this many frames since its last flap.
+const ENTRY_GLIDE: u32 = 32;
+
+// Heights the bird's center can be at after some frames without flapping,
+// all moving at the same velocity.
+struct Tube {
+    top: f32,
+    bottom: f32,
+    frames: u32,
+}
+
+// Whether the bird can fly from anywhere in the gap of `from` through the
+// gap of `to`, with the pipes as they are now and moving at the speed for
+// `score`. Simulates tap-to-flap at game speed 1 (other speeds stretch time
+// evenly for pipes and bird alike), tracking the envelope of reachable
+// heights as one band per frame the bird last flapped on. Bands are merged
+// per open span, so it errs on the side of calling a layout reachable.
+pub fn reachable(from: &Pipe, to: &Pipe, score: i64, floor: f32) -> bool {
+    reachable_through(from, to, score, floor, None)
+}
+
+// Like `reachable`, but the bird also has to collect a coin at `coin`,
+// which scrolls with the pipes, on the way.
+pub fn reachable_through(from: &Pipe, to: &Pipe, score: i64, floor: f32, coin: Option<Vec2>) -> bool {
+    let (mut from, mut to, mut coin) = (from.clone(), to.clone(), coin);
+    let step = |from: &mut Pipe, to: &mut Pipe, coin: &mut Option<Vec2>| {
+        from.go(score as f32, 1.0);
+        to.go(score as f32, 1.0);
+        if let Some(coin) = coin {
+            coin.x -= pipe_speed(score as f32);
+        }
+    };
+    // Keeps the heights that pick up the coin once it reaches the bird.
+    let collect = |tubes: &mut Vec<Tube>, coin: &mut Option<Vec2>| {
+        if let Some(spot) = *coin {
+            if spot.x <= BIRD_CENTER_X {
+                tubes.retain_mut(|tube| {
+                    tube.top = tube.top.max(spot.y - COIN_REACH);
+                    tube.bottom = tube.bottom.min(spot.y + COIN_REACH);
+                    tube.top <= tube.bottom
+                });
+                *coin = None;
+            }
+        }
+    };
+
+    // Start when `from` reaches the bird, with the bird anywhere in its gap
+    // and moving at any speed from a fresh flap to a long glide.
+    let mut frames = 0;
+    while !overlaps(&from) && from.body_upper.x > BIRD_CENTER_X {
+        step(&mut from, &mut to, &mut coin);
+        frames += 1;
+        if frames > MAX_FRAMES {
+            return true;
+        }
+    }
+    let mut spans = vec![];
+    open_spans(&from, &to, floor, &mut spans);
+    let mut tubes: Vec<Tube> = spans
+        .iter()
+        .flat_map(|&(top, bottom)| (0..=ENTRY_GLIDE).map(move |frames| Tube { top, bottom, frames }))
+        .collect();
+    collect(&mut tubes, &mut coin);
+    let mut next = vec![];
+
+    while to.body_upper.x + to.body_upper.w >= BIRD_CENTER_X - RADIUS {
+        step(&mut from, &mut to, &mut coin);
+        open_spans(&from, &to, floor, &mut spans);
+
+        next.clear();
+        for tube in &tubes {
+            let frames = tube.frames + 1;
+            let velocity = JUMP_VELOCITY + frames as f32 * GRAVITY;
+            let (top, bottom) = (tube.top + velocity, tube.bottom + velocity);
+            for &(open_top, open_bottom) in &spans {
+                if top.max(open_top) <= bottom.min(open_bottom) {
+                    next.push(Tube { top: top.max(open_top), bottom: bottom.min(open_bottom), frames });
+                }
+            }
+        }
+        // Flapping from anywhere the bird can be this frame.
+        let moved = next.len();
+        for &(open_top, open_bottom) in &spans {
+            let (mut top, mut bottom) = (f32::INFINITY, f32::NEG_INFINITY);
+            for tube in &next[..moved] {
+                if tube.top >= open_top && tube.bottom <= open_bottom {
+                    top = top.min(tube.top);
+                    bottom = bottom.max(tube.bottom);
+                }
+            }
+            if top <= bottom {
+                next.push(Tube { top, bottom, frames: 0 });
+            }
+        }
+        collect(&mut next, &mut coin);
+        if next.is_empty() {
+            return false;
+        }
+        std::mem::swap(&mut tubes, &mut next);
+    }
+    coin.is_none()
+}
+
+fn overlaps(pipe: &Pipe) -> bool {
+    let x = pipe.body_upper.x;
+    x < BIRD_CENTER_X + RADIUS && x + pipe.body_upper.w > BIRD_CENTER_X - RADIUS
+}
+
+// Heights the bird's center can be at in its column without hitting the
+// ceiling, the floor or a pipe. Pipe rects are grown by the radius, which
+// is slightly stricter than the real circle test at the corners.
+fn open_spans(from: &Pipe, to: &Pipe, floor: f32, spans: &mut Vec<(f32, f32)>) {
+    let mut blocked = [(0.0, 0.0); 6];
+    let mut count = 0;
+    for pipe in [from, to] {
+        if overlaps(pipe) {
+            for rect in pipe.solids() {
+                blocked[count] = (rect.y - RADIUS, rect.y + rect.h + RADIUS);
+                count += 1;
+            }
+        }
+    }
+    let blocked = &mut blocked[..count];
+    blocked.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    spans.clear();
+    let mut top = RADIUS;
+    for &(start, end) in blocked.iter() {
+        if start > top {
+            spans.push((top, start.min(floor - RADIUS)));
+        }
+        top = top.max(end);
+    }
+    spans.push((top, floor - RADIUS));
+    spans.retain(|(top, bottom)| top <= bottom);
+}
+
+#[cfg(test)]
+pub mod tests {
+    use macroquad::rand;
+
+    use super::{reachable, reachable_through};
+    use crate::generator::{Course, CourseKind};
+    use crate::pipe::Pipe;
+
+    const WIDTH: f32 = 800.0;
+    const FLOOR: f32 = 536.0;
+
+    // Test helper: runs `pipes` pipes of the course for every seed, scoring
+    // one point per pipe so the speed goes up, and fails on the first pair of
+    // pipes (or coin) the bird couldn't get through.
+    pub fn assert_winnable(kind: CourseKind, seeds: std::ops::Range<u64>, pipes: i64) {
+        for seed in seeds {
+            rand::srand(seed);
+            let mut course = Course::new(kind);
+            let mut live: Vec<Pipe> = vec![];
+            let mut score = 0;
+            while score < pipes {
+                let (count, before) = (live.len(), live.last().cloned());
+                let coin = course.update(&mut live, true, score, FLOOR, 1.0, WIDTH);
+                if live.len() > count {
+                    if let Some(prev) = before {
+                        assert!(
+                            reachable(&prev, live.last().unwrap(), score, FLOOR),
+                            "{} course, seed {}: pipe {} can't be reached",
+                            kind.name(),
+                            seed,
+                            score + 1
+                        );
+                        if let Some(coin) = coin {
+                            assert!(
+                                reachable_through(&prev, live.last().unwrap(), score, FLOOR, Some(coin.pos)),
+                                "{} course, seed {}: coin before pipe {} can't be collected",
+                                kind.name(),
+                                seed,
+                                score + 1
+                            );
+                        }
+                    }
+                    score += 1;
+                }
+                for pipe in &mut live {
+                    pipe.go(score as f32, 1.0);
+                }
+                live.retain(|pipe| !pipe.edge());
+            }
+        }
+    }
+
+    #[test]
+    fn courses_are_winnable() {
+        for kind in [CourseKind::Random, CourseKind::Walk, CourseKind::Stairs, CourseKind::Zigzag, CourseKind::Chunks, CourseKind::Mixed] {
+            assert_winnable(kind, 0..3000, 30);
+        }
+    }
+}
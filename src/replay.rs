@@ -0,0 +1,222 @@
+use macroquad::prelude::*;
+
+use crate::coin::Coin;
+use crate::level::{Level, LevelRun};
+use crate::pipe::Pipe;
+use crate::{circle_rect_intersect, BIRD_SIZE, BIRD_X, GRAVITY, JUMP_VELOCITY};
+
+// The window is fixed at macroquad's default size, so every game sees the
+// same course for a seed.
+pub const WIDTH: f32 = 800.0;
+pub const HEIGHT: f32 = 600.0;
+pub const FLOOR: f32 = 536.0;
+
+// The last daily run, for --export.
+pub const REPLAY_FILE: &str = "replay.txt";
+
+// Day number since 1970 (UTC), which is also the daily challenge's seed.
+pub fn daily_seed() -> u64 {
+    (macroquad::miniquad::date::now() / 86400.0) as u64
+}
+
+// A run on a seeded course, as sent to the leaderboard: the frames the bird
+// flapped on, counted from the press that started the run. One line of
+// text, `seed score flap...`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub score: i64,
+    pub flaps: Vec<u64>,
+    // Frames played so far while recording.
+    pub ticks: u64,
+}
+
+impl Replay {
+    pub fn new(seed: u64) -> Replay {
+        Replay { seed, score: 0, flaps: vec![0], ticks: 0 }
+    }
+
+    pub fn flap(&mut self) {
+        self.flaps.push(self.ticks);
+    }
+
+    pub fn encode(&self) -> String {
+        let mut text = format!("{} {}", self.seed, self.score);
+        for flap in &self.flaps {
+            text += &format!(" {}", flap);
+        }
+        text
+    }
+
+    pub fn load(path: &str) -> Option<Replay> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                println!("failed to read {}: {}", path, err);
+                return None;
+            }
+        };
+        let replay = Replay::decode(&text);
+        if replay.is_none() {
+            println!("bad replay in {}", path);
+        }
+        replay
+    }
+
+    pub fn save(&self, path: &str) {
+        if let Err(err) = std::fs::write(path, self.encode() + "\n") {
+            println!("failed to write {}: {}", path, err);
+        }
+    }
+
+    pub fn decode(text: &str) -> Option<Replay> {
+        let mut fields = text.split_whitespace();
+        let seed = fields.next()?.parse().ok()?;
+        let score = fields.next()?.parse().ok()?;
+        let flaps: Vec<u64> = fields.map(|flap| flap.parse().ok()).collect::<Option<_>>()?;
+        let ticks = flaps.last().copied().unwrap_or(0);
+        Some(Replay { seed, score, flaps, ticks })
+    }
+}
+
+// Plays the seeded course with the given flaps and no player, and returns
+// the score.
+pub fn simulate(seed: u64, flaps: &[u64]) -> i64 {
+    if flaps.first() != Some(&0) || flaps.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return 0;
+    }
+    let mut flight = Flight::new(seed);
+    let mut flaps = flaps[1..].iter().peekable();
+    // The course ends with a finish line, so this always stops.
+    while flight.flying {
+        let tick = flight.tick + 1;
+        flight.step(flaps.next_if_eq(&&tick).is_some());
+    }
+    flight.score
+}
+
+// One bird on a seeded course, moved a tick at a time the same way the game
+// loop does at game speed 1 with no assists. Tick 0 is the press that
+// starts the run. Also flies the other racers from their flaps.
+#[derive(Clone)]
+pub struct Flight {
+    run: LevelRun,
+    pipes: Vec<Pipe>,
+    coins: Vec<Coin>,
+    pub bird: Rect,
+    velocity: f32,
+    pub score: i64,
+    pub tick: u64,
+    // Until it hits something or crosses the finish line.
+    pub flying: bool,
+    pub crashed: bool,
+}
+
+impl Flight {
+    pub fn new(seed: u64) -> Flight {
+        Flight {
+            run: LevelRun::new(Level::seeded(seed, FLOOR, WIDTH), None, -WIDTH),
+            pipes: vec![],
+            coins: vec![],
+            bird: Rect::new(BIRD_X, HEIGHT / 2.0, BIRD_SIZE, BIRD_SIZE),
+            velocity: JUMP_VELOCITY,
+            score: 0,
+            tick: 0,
+            flying: true,
+            crashed: false,
+        }
+    }
+
+    // Plays the next tick, flapping at the end of it if `flap`.
+    pub fn step(&mut self, flap: bool) {
+        if !self.flying {
+            return;
+        }
+        self.tick += 1;
+        let center = self.bird.center();
+        let mut hit = false;
+        for pipe in &mut self.pipes {
+            pipe.go(self.score as f32, 1.0);
+            hit |= pipe.solids().any(|rect| circle_rect_intersect(center.x, center.y, BIRD_SIZE / 2.0, rect.x, rect.y, rect.w, rect.h));
+            if self.bird.x > pipe.body_lower.x && pipe.scored == 0 {
+                pipe.scored = 1;
+                self.score += 1;
+            }
+        }
+        if hit || self.bird.y + BIRD_SIZE > FLOOR || self.bird.y < 0.0 {
+            self.flying = false;
+            self.crashed = true;
+            return;
+        }
+        self.run.go(self.score as f32, 1.0);
+        self.run.update(&mut self.pipes, &mut self.coins, FLOOR, 1.0, WIDTH);
+        if self.run.finished {
+            self.flying = false;
+            return;
+        }
+        self.pipes.retain(|pipe| !pipe.edge());
+        self.velocity += GRAVITY;
+        self.bird.y += self.velocity;
+        if flap {
+            self.velocity = JUMP_VELOCITY;
+        }
+    }
+
+    // Level x at the left edge of the screen.
+    pub fn scroll(&self) -> f32 {
+        self.run.scroll()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Flaps whenever the bird drops below the middle of the next gap, which
+    // is good enough to get through the first pipes of a seeded course.
+    pub(crate) fn autopilot(seed: u64) -> Replay {
+        let mut run = LevelRun::new(Level::seeded(seed, FLOOR, WIDTH), None, -WIDTH);
+        let (mut pipes, mut coins): (Vec<Pipe>, _) = (vec![], vec![]);
+        let mut replay = Replay::new(seed);
+        let (mut y, mut velocity) = (HEIGHT / 2.0, JUMP_VELOCITY);
+        let mut score = 0;
+        for tick in 1..3000 {
+            for pipe in &mut pipes {
+                pipe.go(score as f32, 1.0);
+                if BIRD_X > pipe.body_lower.x && pipe.scored == 0 {
+                    pipe.scored = 1;
+                    score += 1;
+                }
+            }
+            run.go(score as f32, 1.0);
+            run.update(&mut pipes, &mut coins, FLOOR, 1.0, WIDTH);
+            pipes.retain(|pipe| !pipe.edge());
+            velocity += GRAVITY;
+            y += velocity;
+            let target = pipes
+                .iter()
+                .find(|pipe| pipe.body_upper.right() > BIRD_X)
+                .map_or(HEIGHT / 2.0, |pipe| (pipe.body_upper.bottom() + pipe.body_lower.top()) / 2.0);
+            if y + BIRD_SIZE / 2.0 > target + 20.0 && velocity > 0.0 {
+                velocity = JUMP_VELOCITY;
+                replay.flaps.push(tick);
+            }
+        }
+        replay.score = simulate(seed, &replay.flaps);
+        replay
+    }
+
+    #[test]
+    fn replays_verify_and_doctored_scores_do_not() {
+        let replay = autopilot(20261019);
+        assert!(replay.score >= 3, "the autopilot only scored {}", replay.score);
+        let decoded = Replay::decode(&replay.encode()).unwrap();
+        assert_eq!(simulate(decoded.seed, &decoded.flaps), decoded.score);
+        // Flaps that don't keep the bird up score less than claimed.
+        let cheated = Replay { flaps: decoded.flaps[..5].to_vec(), ..decoded.clone() };
+        assert!(simulate(cheated.seed, &cheated.flaps) < cheated.score);
+        // Without flaps the bird drops to the ground before the first pipe.
+        assert_eq!(simulate(replay.seed, &[0]), 0);
+        assert_eq!(simulate(replay.seed, &[]), 0);
+    }
+}
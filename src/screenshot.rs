@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+
+use macroquad::prelude::*;
+
+pub const SCREENSHOT_KEY: KeyCode = KeyCode::F12;
+
+// Per-channel difference (0-255) still treated as equal when comparing
+// against a golden image, to absorb driver rounding.
+const GOLDEN_TOLERANCE: u8 = 2;
+
+// Grabs what has been drawn so far this frame. Call it after everything is
+// drawn and before next_frame(). The image is stored bottom row first,
+// the way GL reads it back.
+pub fn capture() -> Image {
+    get_screen_data()
+}
+
+// Saves to a timestamped PNG and returns the path.
+pub fn save(image: &Image) -> Option<PathBuf> {
+    let dir = screenshot_dir();
+    if let Err(err) = std::fs::create_dir_all(&dir) {
+        println!("failed to create {}: {}", dir.display(), err);
+        return None;
+    }
+    let path = dir.join(format!("rusty_bird-{}.png", timestamp()));
+    image.export_png(&path.to_string_lossy());
+    println!("saved screenshot {}", path.display());
+    Some(path)
+}
+
+// Golden-image check for rendering tests. On CI this runs on the software
+// GL path through ci/golden.sh, which compares the start screen with
+// tests/golden/start.png.
+pub async fn matches_golden(image: &Image, golden: &str) -> Result<(), String> {
+    let expected = load_image(golden).await.map_err(|err| format!("failed to load {}: {}", golden, err))?;
+    if (expected.width, expected.height) != (image.width, image.height) {
+        return Err(format!(
+            "size differs: got {}x{}, golden is {}x{}",
+            image.width, image.height, expected.width, expected.height
+        ));
+    }
+    let (w, h) = (image.width as usize, image.height as usize);
+    let got = image.get_image_data();
+    let want = expected.get_image_data();
+    let mut bad = 0;
+    for y in 0..h {
+        for x in 0..w {
+            // The capture is bottom-up, the loaded PNG top-down.
+            let a = got[(h - 1 - y) * w + x];
+            let b = want[y * w + x];
+            if a.iter().zip(b.iter()).any(|(a, b)| a.abs_diff(*b) > GOLDEN_TOLERANCE) {
+                bad += 1;
+            }
+        }
+    }
+    if bad > 0 {
+        return Err(format!("{} of {} pixels differ from {}", bad, w * h, golden));
+    }
+    Ok(())
+}
+
+// The user's pictures folder if there is one, otherwise the game's data
+// directory.
+fn screenshot_dir() -> PathBuf {
+    let env = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let home = env("HOME").or_else(|| env("USERPROFILE"));
+
+    let pictures = env("XDG_PICTURES_DIR").or_else(|| home.as_ref().map(|h| h.join("Pictures")));
+    if let Some(dir) = pictures.filter(|dir| dir.is_dir()) {
+        return dir.join("Rusty Bird");
+    }
+    let data = env("XDG_DATA_HOME")
+        .or_else(|| env("APPDATA"))
+        .or_else(|| home.map(|h| h.join(".local").join("share")))
+        .unwrap_or_else(|| PathBuf::from("."));
+    data.join("rusty_bird").join("screenshots")
+}
+
+// UTC, e.g. 20261019-153012-042, so names sort by time and don't collide
+// when several shots are taken in the same second.
+fn timestamp() -> String {
+    let now = macroquad::miniquad::date::now();
+    let secs = now as i64;
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}",
+        year, month, day,
+        rem / 3600, rem % 3600 / 60, rem % 60,
+        (now.fract() * 1000.0) as i64,
+    )
+}
@@ -0,0 +1,136 @@
+use std::fs;
+
+use crate::generator::CourseKind;
+use crate::sky::DayCycleMode;
+use crate::weather::WeatherKind;
+
+pub const SETTINGS_FILE: &str = "settings.txt";
+
+// Player preferences, stored as `key = value` lines in settings.txt next to
+// the game. Missing or unknown keys fall back to the defaults below.
+#[derive(Clone)]
+pub struct Settings {
+    pub screen_shake: bool,
+    pub hit_stop: bool,
+    pub flash: bool,
+    pub death_zoom: bool,
+    pub day_cycle: DayCycleMode,
+    // Hours added to UTC when day_cycle = clock.
+    pub utc_offset: f32,
+    // None rolls a random kind each run.
+    pub weather: Option<WeatherKind>,
+    // Language code for strings.<language>.txt, or "auto" for the system locale.
+    pub language: String,
+    // Bus levels from 0 to 1.
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub mute: bool,
+    // Which PipeGenerator places the pipes.
+    pub course: CourseKind,
+
+    // Accessibility. Runs with any of the first three changed from their
+    // defaults count as assisted (see `assisted`).
+    pub game_speed: f32,
+    pub gap_assist: f32,
+    pub hold_to_hover: bool,
+    pub high_contrast: bool,
+    pub colorblind: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            screen_shake: true,
+            hit_stop: true,
+            flash: true,
+            death_zoom: true,
+            day_cycle: DayCycleMode::Run,
+            utc_offset: 0.0,
+            weather: Some(WeatherKind::Clear),
+            language: "auto".to_owned(),
+            master_volume: 1.0,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            mute: false,
+            course: CourseKind::Random,
+            game_speed: 1.0,
+            gap_assist: 1.0,
+            hold_to_hover: false,
+            high_contrast: false,
+            colorblind: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Settings {
+        let mut settings = Settings::default();
+        let Ok(text) = fs::read_to_string(SETTINGS_FILE) else {
+            return settings;
+        };
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let ok = match line.split_once('=') {
+                Some((key, value)) => settings.set(key.trim(), value.trim()).is_some(),
+                None => false,
+            };
+            if !ok {
+                println!("{}:{}: bad setting: {}", SETTINGS_FILE, n + 1, line);
+            }
+        }
+        settings
+    }
+
+    pub fn save(&self) {
+        let weather = match self.weather {
+            Some(kind) => kind.name(),
+            None => "random",
+        };
+        let text = format!(
+            "screen_shake = {}\nhit_stop = {}\nflash = {}\ndeath_zoom = {}\n\
+             day_cycle = {}\nutc_offset = {}\nweather = {}\nlanguage = {}\n\
+             master_volume = {}\nmusic_volume = {}\nsfx_volume = {}\nmute = {}\ncourse = {}\n\
+             game_speed = {}\ngap_assist = {}\nhold_to_hover = {}\nhigh_contrast = {}\ncolorblind = {}\n",
+            self.screen_shake, self.hit_stop, self.flash, self.death_zoom,
+            self.day_cycle.name(), self.utc_offset, weather, self.language,
+            self.master_volume, self.music_volume, self.sfx_volume, self.mute, self.course.name(),
+            self.game_speed, self.gap_assist, self.hold_to_hover, self.high_contrast, self.colorblind,
+        );
+        if let Err(err) = fs::write(SETTINGS_FILE, text) {
+            println!("failed to save {}: {}", SETTINGS_FILE, err);
+        }
+    }
+
+    pub fn assisted(&self) -> bool {
+        self.game_speed < 1.0 || self.gap_assist > 1.0 || self.hold_to_hover
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Option<()> {
+        match key {
+            "screen_shake" => self.screen_shake = value.parse().ok()?,
+            "hit_stop" => self.hit_stop = value.parse().ok()?,
+            "flash" => self.flash = value.parse().ok()?,
+            "death_zoom" => self.death_zoom = value.parse().ok()?,
+            "day_cycle" => self.day_cycle = DayCycleMode::parse(value)?,
+            "utc_offset" => self.utc_offset = value.parse().ok()?,
+            "weather" => self.weather = WeatherKind::parse(value)?,
+            "language" => self.language = value.to_owned(),
+            "master_volume" => self.master_volume = value.parse::<f32>().ok()?.clamp(0.0, 1.0),
+            "music_volume" => self.music_volume = value.parse::<f32>().ok()?.clamp(0.0, 1.0),
+            "sfx_volume" => self.sfx_volume = value.parse::<f32>().ok()?.clamp(0.0, 1.0),
+            "mute" => self.mute = value.parse().ok()?,
+            "course" => self.course = CourseKind::parse(value)?,
+            "game_speed" => self.game_speed = value.parse::<f32>().ok()?.clamp(0.5, 1.0),
+            "gap_assist" => self.gap_assist = value.parse::<f32>().ok()?.clamp(1.0, 1.5),
+            "hold_to_hover" => self.hold_to_hover = value.parse().ok()?,
+            "high_contrast" => self.high_contrast = value.parse().ok()?,
+            "colorblind" => self.colorblind = value.parse().ok()?,
+            _ => return None,
+        }
+        Some(())
+    }
+}
@@ -0,0 +1,514 @@
+use std::fs;
+
+use macroquad::audio::{load_sound, Sound};
+use macroquad::prelude::*;
+
+use crate::accessibility::Palette;
+use crate::assets::{Assets, ASSET_DIR};
+use crate::level::Campaign;
+use crate::locale::Strings;
+use crate::parallax::Parallax;
+use crate::text::{ui_scale, Align, Fonts, TextStyle};
+use crate::wallet::Wallet;
+
+pub const SKIN_DIR: &str = "skins";
+pub const SKIN_FILE: &str = "skin.txt";
+pub const SKINS_FILE: &str = "skins.txt";
+pub const BACKGROUND_DIR: &str = "backgrounds";
+pub const BACKGROUND_FILE: &str = "background.txt";
+
+// The sprites in the top level of assets, always available.
+const CLASSIC: &str = "classic";
+
+#[derive(Clone, PartialEq)]
+pub enum Unlock {
+    Free,
+    // Bought from the wallet.
+    Coins(i64),
+    // Best endless score without assists.
+    Score(i64),
+    // Finishing this campaign level, counted from 1.
+    Level(usize),
+    // Stars earned over the whole campaign.
+    Stars(u32),
+}
+
+impl Unlock {
+    fn parse(value: &str) -> Option<Unlock> {
+        let (kind, n) = value.split_once(' ')?;
+        let n = n.trim();
+        Some(match kind {
+            "coins" => Unlock::Coins(n.parse().ok()?),
+            "score" => Unlock::Score(n.parse().ok()?),
+            "level" => Unlock::Level(n.parse().ok().filter(|&n| n > 0)?),
+            "stars" => Unlock::Stars(n.parse().ok()?),
+            _ => return None,
+        })
+    }
+
+    fn describe(&self, strings: &Strings) -> String {
+        match *self {
+            Unlock::Free => String::new(),
+            Unlock::Coins(n) => strings.plural("unlock.coins", n),
+            Unlock::Score(n) => strings.plural("unlock.score", n),
+            Unlock::Level(n) => strings.plural("unlock.level", n as i64),
+            Unlock::Stars(n) => strings.plural("unlock.stars", n as i64),
+        }
+    }
+}
+
+// One look for the bird, from a folder in assets/skins (see the comment in
+// skins/cardinal/skin.txt for the format).
+pub struct Skin {
+    // Folder name, used in skins.txt.
+    pub id: String,
+    pub name: String,
+    flappy: Option<Texture2D>,
+    flappy_jump: Option<Texture2D>,
+    // Particle effect emitted behind the bird while flying.
+    pub trail: Option<String>,
+    pub death: Option<Sound>,
+    pub unlock: Unlock,
+}
+
+impl Skin {
+    fn classic() -> Skin {
+        Skin {
+            id: CLASSIC.to_owned(),
+            name: "Classic".to_owned(),
+            flappy: None,
+            flappy_jump: None,
+            trail: None,
+            death: None,
+            unlock: Unlock::Free,
+        }
+    }
+
+    async fn load(id: &str) -> Option<Skin> {
+        let dir = format!("{}/{}/{}", ASSET_DIR, SKIN_DIR, id);
+        let text = fs::read_to_string(format!("{}/{}", dir, SKIN_FILE)).ok()?;
+        let mut skin = Skin { id: id.to_owned(), name: id.to_owned(), ..Skin::classic() };
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let ok = match line.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+                Some(("name", value)) => {
+                    skin.name = value.to_owned();
+                    true
+                }
+                Some(("trail", value)) => {
+                    skin.trail = Some(value.to_owned());
+                    true
+                }
+                Some(("unlock", value)) => Unlock::parse(value).map(|unlock| skin.unlock = unlock).is_some(),
+                _ => false,
+            };
+            if !ok {
+                println!("{}/{}:{}: bad line: {}", id, SKIN_FILE, n + 1, line);
+            }
+        }
+        // Everything else is optional; missing files fall back to the classic look.
+        skin.flappy = load_texture(&format!("{}/flappy.png", dir)).await.ok();
+        skin.flappy_jump = load_texture(&format!("{}/flappy_jump.png", dir)).await.ok();
+        skin.death = load_sound(&format!("{}/death.wav", dir)).await.ok();
+        Some(skin)
+    }
+
+    // Resting and flapping sprites.
+    pub fn textures<'a>(&'a self, assets: &'a Assets) -> (&'a Texture2D, &'a Texture2D) {
+        (
+            self.flappy.as_ref().unwrap_or(&assets.flappy),
+            self.flappy_jump.as_ref().unwrap_or(&assets.flappy_jump),
+        )
+    }
+}
+
+// Parallax layers behind the course, from a folder in assets/backgrounds
+// (see the comment in backgrounds/night/background.txt for the format).
+pub struct Background {
+    // Folder name, used in skins.txt.
+    pub id: String,
+    pub name: String,
+    pub unlock: Unlock,
+    // Also drawn on the shop card.
+    pub layers: Parallax,
+}
+
+impl Background {
+    // The layers in the top level of assets.
+    async fn classic() -> Background {
+        Background { id: CLASSIC.to_owned(), name: "Classic".to_owned(), unlock: Unlock::Free, layers: Parallax::load("").await }
+    }
+
+    async fn load(id: &str) -> Option<Background> {
+        let text = fs::read_to_string(format!("{}/{}/{}/{}", ASSET_DIR, BACKGROUND_DIR, id, BACKGROUND_FILE)).ok()?;
+        let mut name = id.to_owned();
+        let mut unlock = Unlock::Free;
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let ok = match line.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+                Some(("name", value)) => {
+                    name = value.to_owned();
+                    true
+                }
+                Some(("unlock", value)) => Unlock::parse(value).map(|value| unlock = value).is_some(),
+                _ => false,
+            };
+            if !ok {
+                println!("{}/{}:{}: bad line: {}", id, BACKGROUND_FILE, n + 1, line);
+            }
+        }
+        let layers = Parallax::load(&Background::dir_of(id)).await;
+        Some(Background { id: id.to_owned(), name, unlock, layers })
+    }
+
+    fn dir_of(id: &str) -> String {
+        if id == CLASSIC { String::new() } else { format!("{}/{}", BACKGROUND_DIR, id) }
+    }
+
+    // For Parallax::load.
+    pub fn dir(&self) -> String {
+        Background::dir_of(&self.id)
+    }
+}
+
+fn folders(dir: &str) -> Vec<String> {
+    let mut ids: Vec<String> = fs::read_dir(format!("{}/{}", ASSET_DIR, dir))
+        .map(|entries| entries.flatten().map(|entry| entry.file_name().to_string_lossy().into_owned()).collect())
+        .unwrap_or_default();
+    ids.sort();
+    ids.retain(|id| id != CLASSIC);
+    ids
+}
+
+// Every skin in assets/skins and background in assets/backgrounds, plus the
+// classic ones, and which of them the player owns and uses. Those bought
+// with coins are remembered in skins.txt; the others unlock whenever their
+// condition holds.
+pub struct Skins {
+    pub skins: Vec<Skin>,
+    selected: usize,
+    bought: Vec<String>,
+    pub backgrounds: Vec<Background>,
+    background: usize,
+    bought_backgrounds: Vec<String>,
+    // Best endless score over all sessions, for Unlock::Score.
+    best: i64,
+}
+
+impl Skins {
+    pub async fn load() -> Skins {
+        let mut skins = vec![Skin::classic()];
+        for id in folders(SKIN_DIR) {
+            match Skin::load(&id).await {
+                Some(skin) => skins.push(skin),
+                None => println!("skipping {}/{}: no {}", SKIN_DIR, id, SKIN_FILE),
+            }
+        }
+        let mut backgrounds = vec![Background::classic().await];
+        for id in folders(BACKGROUND_DIR) {
+            match Background::load(&id).await {
+                Some(background) => backgrounds.push(background),
+                None => println!("skipping {}/{}: no {}", BACKGROUND_DIR, id, BACKGROUND_FILE),
+            }
+        }
+
+        let mut registry = Skins { skins, selected: 0, bought: vec![], backgrounds, background: 0, bought_backgrounds: vec![], best: 0 };
+        let Ok(text) = fs::read_to_string(SKINS_FILE) else {
+            return registry;
+        };
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let ok = match line.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+                Some(("selected", value)) => {
+                    registry.selected = registry.skins.iter().position(|skin| skin.id == value).unwrap_or(0);
+                    true
+                }
+                Some(("bought", value)) => {
+                    registry.bought = value.split_whitespace().map(str::to_owned).collect();
+                    true
+                }
+                Some(("background", value)) => {
+                    registry.background = registry.backgrounds.iter().position(|background| background.id == value).unwrap_or(0);
+                    true
+                }
+                Some(("bought_backgrounds", value)) => {
+                    registry.bought_backgrounds = value.split_whitespace().map(str::to_owned).collect();
+                    true
+                }
+                Some(("best", value)) => value.parse().map(|best| registry.best = best).is_ok(),
+                _ => false,
+            };
+            if !ok {
+                println!("{}:{}: bad line: {}", SKINS_FILE, n + 1, line);
+            }
+        }
+        registry
+    }
+
+    // Loads a skin or background folder again when anything in it changes,
+    // or picks up a new one.
+    pub async fn reload(&mut self, name: &str) {
+        let folder = |dir: &str| name.strip_prefix(dir).and_then(|rest| rest.strip_prefix('/')).and_then(|rest| rest.split_once('/')).map(|(id, _)| id.to_owned());
+        if let Some(id) = folder(BACKGROUND_DIR) {
+            let Some(background) = Background::load(&id).await else {
+                return;
+            };
+            match self.backgrounds.iter().position(|old| old.id == id) {
+                Some(index) => self.backgrounds[index] = background,
+                None => self.backgrounds.push(background),
+            }
+            println!("reloaded {}", name);
+            return;
+        }
+        let Some(id) = folder(SKIN_DIR) else {
+            return;
+        };
+        let id = id.as_str();
+        let Some(skin) = Skin::load(id).await else {
+            return;
+        };
+        match self.skins.iter().position(|old| old.id == id) {
+            Some(index) => self.skins[index] = skin,
+            None => self.skins.push(skin),
+        }
+        println!("reloaded {}", name);
+    }
+
+    fn save(&self) {
+        let text = format!(
+            "selected = {}\nbought = {}\nbackground = {}\nbought_backgrounds = {}\nbest = {}\n",
+            self.skins[self.selected].id,
+            self.bought.join(" "),
+            self.backgrounds[self.background].id,
+            self.bought_backgrounds.join(" "),
+            self.best
+        );
+        if let Err(err) = fs::write(SKINS_FILE, text) {
+            println!("failed to save {}: {}", SKINS_FILE, err);
+        }
+    }
+
+    pub fn current(&self) -> &Skin {
+        &self.skins[self.selected]
+    }
+
+    // The skin with this folder name, or the classic one.
+    pub fn find(&self, id: &str) -> &Skin {
+        self.skins.iter().find(|skin| skin.id == id).unwrap_or(&self.skins[0])
+    }
+
+    pub fn background(&self) -> &Background {
+        &self.backgrounds[self.background]
+    }
+
+    pub fn unlocked(&self, index: usize, campaign: &Campaign) -> bool {
+        let skin = &self.skins[index];
+        self.meets(&skin.unlock, self.bought.contains(&skin.id), campaign)
+    }
+
+    pub fn background_unlocked(&self, index: usize, campaign: &Campaign) -> bool {
+        let background = &self.backgrounds[index];
+        self.meets(&background.unlock, self.bought_backgrounds.contains(&background.id), campaign)
+    }
+
+    fn meets(&self, unlock: &Unlock, bought: bool, campaign: &Campaign) -> bool {
+        match *unlock {
+            Unlock::Free => true,
+            Unlock::Coins(_) => bought,
+            Unlock::Score(n) => self.best >= n,
+            Unlock::Level(n) => n <= campaign.levels.len() && campaign.progress(n - 1).finished,
+            Unlock::Stars(n) => (0..campaign.levels.len()).map(|index| campaign.progress(index).stars).sum::<u32>() >= n,
+        }
+    }
+
+    // Keeps the best unassisted endless score, which unlocks Unlock::Score
+    // skins.
+    pub fn record(&mut self, score: i64) {
+        if score > self.best {
+            self.best = score;
+            self.save();
+        }
+    }
+
+    // Wears the skin, buying it first if it costs coins and the wallet has
+    // enough. Returns false if it's still locked.
+    pub fn choose(&mut self, index: usize, campaign: &Campaign, wallet: &mut Wallet) -> bool {
+        if !self.unlocked(index, campaign) {
+            let Unlock::Coins(price) = self.skins[index].unlock else {
+                return false;
+            };
+            if wallet.coins < price {
+                return false;
+            }
+            wallet.add(-price);
+            wallet.save();
+            self.bought.push(self.skins[index].id.clone());
+        }
+        self.selected = index;
+        self.save();
+        true
+    }
+
+    // The same for backgrounds.
+    pub fn choose_background(&mut self, index: usize, campaign: &Campaign, wallet: &mut Wallet) -> bool {
+        if !self.background_unlocked(index, campaign) {
+            let Unlock::Coins(price) = self.backgrounds[index].unlock else {
+                return false;
+            };
+            if wallet.coins < price {
+                return false;
+            }
+            wallet.add(-price);
+            wallet.save();
+            self.bought_backgrounds.push(self.backgrounds[index].id.clone());
+        }
+        self.background = index;
+        self.save();
+        true
+    }
+}
+
+const CARD_WIDTH: f32 = 170.0;
+
+// Skin and background selection screen opened with S from the start
+// screen. Up/Down switch between skins and backgrounds, Left/Right browse,
+// Enter uses or buys the highlighted one.
+pub struct Shop {
+    pub open: bool,
+    backgrounds: bool,
+    cursor: usize,
+    // Time of the last failed purchase, to flash the price.
+    denied: f64,
+    // As of the last update, for drawing.
+    unlocked: Vec<bool>,
+    coins: i64,
+}
+
+impl Shop {
+    pub fn new() -> Shop {
+        Shop { open: false, backgrounds: false, cursor: 0, denied: f64::MIN, unlocked: vec![], coins: 0 }
+    }
+
+    // Returns true when a skin or background was chosen.
+    pub fn update(&mut self, skins: &mut Skins, campaign: &Campaign, wallet: &mut Wallet) -> bool {
+        if is_key_pressed(KeyCode::S) || (self.open && is_key_pressed(KeyCode::Escape)) {
+            self.open = !self.open;
+            self.backgrounds = false;
+            self.cursor = skins.selected;
+            return false;
+        }
+        if !self.open {
+            return false;
+        }
+        if is_key_pressed(KeyCode::Up) || is_key_pressed(KeyCode::Down) {
+            self.backgrounds = !self.backgrounds;
+            self.cursor = if self.backgrounds { skins.background } else { skins.selected };
+        }
+        let count = if self.backgrounds { skins.backgrounds.len() } else { skins.skins.len() };
+        if is_key_pressed(KeyCode::Right) {
+            self.cursor = (self.cursor + 1) % count;
+        }
+        if is_key_pressed(KeyCode::Left) {
+            self.cursor = (self.cursor + count - 1) % count;
+        }
+        let enter = is_key_pressed(KeyCode::Enter);
+        let chosen = enter
+            && if self.backgrounds {
+                skins.choose_background(self.cursor, campaign, wallet)
+            } else {
+                skins.choose(self.cursor, campaign, wallet)
+            };
+        if enter && !chosen {
+            self.denied = get_time();
+        }
+        self.unlocked = (0..count)
+            .map(|index| if self.backgrounds { skins.background_unlocked(index, campaign) } else { skins.unlocked(index, campaign) })
+            .collect();
+        self.coins = wallet.coins;
+        chosen
+    }
+
+    pub fn draw(&self, skins: &Skins, assets: &Assets, fonts: &Fonts, strings: &Strings, palette: &Palette) {
+        draw_rectangle(0.0, 0.0, screen_width(), screen_height(), Color::new(0.0, 0.0, 0.0, 0.75));
+        let scale = ui_scale();
+        let text = TextStyle {
+            color: palette.text,
+            align: Align::Center,
+            anchor: vec2(0.5, 0.0),
+            outline: Some((2.0, palette.outline)),
+            ..Default::default()
+        };
+        let title = if self.backgrounds { "shop.backgrounds" } else { "shop.title" };
+        fonts.draw(strings.get(title), &TextStyle { size: 40.0, offset: vec2(0.0, 60.0), ..text });
+        fonts.draw(&strings.plural("coins.wallet", self.coins), &TextStyle { size: 24.0, offset: vec2(0.0, 110.0), ..text });
+
+        let (count, current) = if self.backgrounds { (skins.backgrounds.len(), skins.background) } else { (skins.skins.len(), skins.selected) };
+        // The cursor's card stays in the middle with its neighbours to the sides.
+        for index in 0..count {
+            let column = index as f32 - self.cursor as f32;
+            if column.abs() > 2.0 {
+                continue;
+            }
+            let x = column * CARD_WIDTH;
+            let center = vec2(screen_width() / 2.0 + x * scale, 260.0 * scale);
+            let unlocked = self.unlocked.get(index).copied().unwrap_or(false);
+            let color = if index == self.cursor { palette.highlight } else { palette.text };
+            let (w, h) = ((CARD_WIDTH - 20.0) * scale, 220.0 * scale);
+            draw_rectangle(center.x - w / 2.0, center.y - 80.0 * scale, w, h, Color::new(1.0, 1.0, 1.0, 0.1));
+            draw_rectangle_lines(center.x - w / 2.0, center.y - 80.0 * scale, w, h, 3.0 * scale, color);
+
+            let tint = if unlocked { WHITE } else { Color::new(0.2, 0.2, 0.2, 1.0) };
+            let (name, unlock) = if self.backgrounds {
+                let background = &skins.backgrounds[index];
+                // The layers squeezed into a little window, over the day sky.
+                let size = vec2(w - 20.0 * scale, 110.0 * scale);
+                let corner = center - size / 2.0;
+                draw_rectangle(corner.x, corner.y, size.x, size.y, Color::new(SKYBLUE.r * tint.r, SKYBLUE.g * tint.g, SKYBLUE.b * tint.b, 1.0));
+                for layer in &background.layers.layers {
+                    draw_texture_ex(&layer.texture, corner.x, corner.y, tint, DrawTextureParams {
+                        dest_size: Some(size),
+                        ..Default::default()
+                    });
+                }
+                (&background.name, &background.unlock)
+            } else {
+                let skin = &skins.skins[index];
+                let (flappy, _) = skin.textures(assets);
+                let size = vec2(90.0, 90.0 * flappy.height() / flappy.width()) * scale;
+                draw_texture_ex(flappy, center.x - size.x / 2.0, center.y - size.y / 2.0, tint, DrawTextureParams {
+                    dest_size: Some(size),
+                    ..Default::default()
+                });
+                (&skin.name, &skin.unlock)
+            };
+
+            fonts.draw(name, &TextStyle { size: 22.0, color, offset: vec2(x, 320.0), max_width: Some(CARD_WIDTH - 30.0), ..text });
+            let status = if index == current {
+                strings.get(if self.backgrounds { "shop.using" } else { "shop.wearing" }).to_owned()
+            } else if unlocked {
+                strings.get("shop.owned").to_owned()
+            } else {
+                unlock.describe(strings)
+            };
+            let flash = index == self.cursor && get_time() - self.denied < 0.4;
+            fonts.draw(&status, &TextStyle {
+                size: 16.0,
+                color: if flash { palette.off } else { color },
+                offset: vec2(x, 350.0),
+                max_width: Some(CARD_WIDTH - 30.0),
+                ..text
+            });
+        }
+
+        fonts.draw(strings.get("shop.hint"), &TextStyle { size: 18.0, offset: vec2(0.0, 520.0), max_width: Some(760.0), ..text });
+    }
+}
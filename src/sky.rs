@@ -0,0 +1,101 @@
+use macroquad::prelude::*;
+
+// Length of a full day when the cycle follows the run, in seconds.
+const DAY_LENGTH: f32 = 120.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DayCycleMode {
+    Off,
+    // A day passes over the course of a run, starting in the morning.
+    Run,
+    // Follows the wall clock, shifted by Settings::utc_offset.
+    Clock,
+}
+
+impl DayCycleMode {
+    pub fn parse(value: &str) -> Option<DayCycleMode> {
+        match value {
+            "off" => Some(DayCycleMode::Off),
+            "run" => Some(DayCycleMode::Run),
+            "clock" => Some(DayCycleMode::Clock),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DayCycleMode::Off => "off",
+            DayCycleMode::Run => "run",
+            DayCycleMode::Clock => "clock",
+        }
+    }
+}
+
+// (time of day in 0..1 with 0 = midnight, tint, sky color)
+const KEYS: [(f32, Color, Color); 6] = [
+    (0.00, Color::new(0.35, 0.40, 0.65, 1.0), Color::new(0.03, 0.05, 0.15, 1.0)),
+    (0.22, Color::new(0.35, 0.40, 0.65, 1.0), Color::new(0.03, 0.05, 0.15, 1.0)),
+    (0.30, Color::new(1.00, 0.80, 0.70, 1.0), Color::new(0.90, 0.55, 0.40, 1.0)),
+    (0.40, Color::new(1.00, 1.00, 1.00, 1.0), Color::new(0.00, 0.32, 0.67, 1.0)),
+    (0.70, Color::new(1.00, 1.00, 1.00, 1.0), Color::new(0.00, 0.32, 0.67, 1.0)),
+    (0.80, Color::new(1.00, 0.70, 0.50, 1.0), Color::new(0.85, 0.40, 0.30, 1.0)),
+];
+
+pub struct DayCycle {
+    pub mode: DayCycleMode,
+    utc_offset: f32,
+    elapsed: f32,
+}
+
+impl DayCycle {
+    pub fn new(mode: DayCycleMode, utc_offset: f32) -> DayCycle {
+        DayCycle { mode, utc_offset, elapsed: 0.0 }
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.elapsed += dt;
+    }
+
+    fn time_of_day(&self) -> f32 {
+        match self.mode {
+            DayCycleMode::Off => 0.5,
+            DayCycleMode::Run => (0.3 + self.elapsed / DAY_LENGTH).fract(),
+            DayCycleMode::Clock => {
+                let seconds = macroquad::miniquad::date::now() + self.utc_offset as f64 * 3600.0;
+                (seconds / 86400.0).fract() as f32
+            }
+        }
+    }
+
+    // Multiplied into the background and ground textures.
+    pub fn tint(&self) -> Color {
+        self.sample().0
+    }
+
+    pub fn sky_color(&self) -> Color {
+        self.sample().1
+    }
+
+    fn sample(&self) -> (Color, Color) {
+        let t = self.time_of_day();
+        let next = KEYS.iter().position(|key| key.0 > t).unwrap_or(KEYS.len());
+        let (t0, tint0, sky0) = KEYS[next - 1];
+        // After the last key, blend back into midnight.
+        let (t1, tint1, sky1) = if next < KEYS.len() { KEYS[next] } else { (1.0, KEYS[0].1, KEYS[0].2) };
+        let f = (t - t0) / (t1 - t0);
+        (mix(tint0, tint1, f), mix(sky0, sky1, f))
+    }
+}
+
+fn mix(a: Color, b: Color, f: f32) -> Color {
+    Color::new(
+        a.r + (b.r - a.r) * f,
+        a.g + (b.g - a.g) * f,
+        a.b + (b.b - a.b) * f,
+        a.a + (b.a - a.a) * f,
+    )
+}
@@ -0,0 +1,240 @@
+use macroquad::prelude::*;
+
+use crate::assets::{texture, ASSET_DIR};
+
+// Layout is done in a fixed virtual resolution and scaled to the window,
+// so sizes and offsets below don't depend on the actual screen size.
+pub const VIRTUAL_WIDTH: f32 = 800.0;
+pub const VIRTUAL_HEIGHT: f32 = 600.0;
+
+pub fn ui_scale() -> f32 {
+    (screen_width() / VIRTUAL_WIDTH).min(screen_height() / VIRTUAL_HEIGHT)
+}
+
+const UI_FONT: &str = "ui.ttf";
+const DIGITS_FONT: &str = "digits.txt";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FontKind {
+    Ui,
+    // The bitmap numerals used for the score. Text with characters the
+    // bitmap font doesn't have falls back to the UI font.
+    Digits,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+// Where and how to draw a piece of text. The text is aligned horizontally
+// on `anchor + offset` and centered vertically on it.
+#[derive(Clone, Copy)]
+pub struct TextStyle {
+    pub font: FontKind,
+    // Line height in virtual pixels.
+    pub size: f32,
+    pub color: Color,
+    pub align: Align,
+    // Fraction of the screen, (0, 0) = top left, (1, 1) = bottom right.
+    pub anchor: Vec2,
+    // Virtual pixels from the anchor.
+    pub offset: Vec2,
+    // Thickness in virtual pixels.
+    pub outline: Option<(f32, Color)>,
+    // Offset in virtual pixels.
+    pub shadow: Option<(Vec2, Color)>,
+    // Shrinks the text to fit this many virtual pixels, for translations
+    // that run longer than the English.
+    pub max_width: Option<f32>,
+}
+
+impl Default for TextStyle {
+    fn default() -> TextStyle {
+        TextStyle {
+            font: FontKind::Ui,
+            size: 40.0,
+            color: WHITE,
+            align: Align::Left,
+            anchor: Vec2::ZERO,
+            offset: Vec2::ZERO,
+            outline: None,
+            shadow: None,
+            max_width: None,
+        }
+    }
+}
+
+// Fixed-cell bitmap font: a row (or grid) of equally sized glyphs in one
+// texture, described by a small text file:
+//
+//   texture digits.png
+//   cell 7 9
+//   chars 0123456789
+pub struct BitmapFont {
+    texture: Texture2D,
+    texture_name: String,
+    cell: Vec2,
+    chars: Vec<char>,
+}
+
+impl BitmapFont {
+    pub async fn load(name: &str) -> Option<BitmapFont> {
+        let text = std::fs::read_to_string(format!("{}/{}", ASSET_DIR, name)).ok()?;
+        let (mut texture_name, mut cell, mut chars) = (None, None, None);
+        for line in text.lines().map(str::trim) {
+            if line.starts_with('#') {
+                continue;
+            }
+            match line.split_once(' ') {
+                Some(("texture", value)) => texture_name = Some(value.trim().to_owned()),
+                Some(("cell", value)) => {
+                    let mut size = value.split_whitespace().filter_map(|v| v.parse::<f32>().ok());
+                    cell = Some(vec2(size.next()?, size.next()?));
+                }
+                Some(("chars", value)) => chars = Some(value.trim().chars().collect()),
+                _ => {}
+            }
+        }
+        let texture_name = texture_name?;
+        let texture = texture(&texture_name).await.ok()?;
+        texture.set_filter(FilterMode::Nearest);
+        Some(BitmapFont { texture, texture_name, cell: cell?, chars: chars? })
+    }
+
+    fn has_all(&self, text: &str) -> bool {
+        text.chars().all(|c| c == ' ' || self.chars.contains(&c))
+    }
+
+    fn measure(&self, text: &str, size: f32) -> f32 {
+        text.chars().count() as f32 * self.cell.x * size / self.cell.y
+    }
+
+    fn draw(&self, text: &str, x: f32, top: f32, size: f32, color: Color) {
+        let glyph = vec2(self.cell.x * size / self.cell.y, size);
+        let columns = (self.texture.width() / self.cell.x).max(1.0) as usize;
+        for (n, c) in text.chars().enumerate() {
+            let Some(index) = self.chars.iter().position(|&ch| ch == c) else {
+                continue;
+            };
+            let source = Rect::new(
+                (index % columns) as f32 * self.cell.x,
+                (index / columns) as f32 * self.cell.y,
+                self.cell.x,
+                self.cell.y,
+            );
+            draw_texture_ex(
+                &self.texture,
+                x + n as f32 * glyph.x,
+                top,
+                color,
+                DrawTextureParams {
+                    dest_size: Some(glyph),
+                    source: Some(source),
+                    ..Default::default()
+                }
+            );
+        }
+    }
+}
+
+pub struct Fonts {
+    ui: Option<Font>,
+    digits: Option<BitmapFont>,
+}
+
+impl Fonts {
+    // Missing fonts fall back to macroquad's built-in font.
+    pub async fn load() -> Fonts {
+        let ui = match load_ttf_font(&format!("{}/{}", ASSET_DIR, UI_FONT)).await {
+            Ok(font) => Some(font),
+            Err(err) => {
+                println!("failed to load {}: {}", UI_FONT, err);
+                None
+            }
+        };
+        Fonts { ui, digits: BitmapFont::load(DIGITS_FONT).await }
+    }
+
+    pub async fn reload(&mut self, name: &str) {
+        let digits_texture = self.digits.as_ref().map(|font| font.texture_name.as_str());
+        if name == UI_FONT || name == DIGITS_FONT || Some(name) == digits_texture {
+            *self = Fonts::load().await;
+            println!("reloaded {}", name);
+        }
+    }
+
+    fn bitmap(&self, text: &str, style: &TextStyle) -> Option<&BitmapFont> {
+        match style.font {
+            FontKind::Digits => self.digits.as_ref().filter(|font| font.has_all(text)),
+            FontKind::Ui => None,
+        }
+    }
+
+    // Size of the text on screen, in screen pixels.
+    pub fn measure(&self, text: &str, style: &TextStyle) -> Vec2 {
+        let size = style.size * ui_scale();
+        match self.bitmap(text, style) {
+            Some(font) => vec2(font.measure(text, size), size),
+            None => {
+                let dims = measure_text(text, self.ui.as_ref(), size as u16, 1.0);
+                vec2(dims.width, dims.height)
+            }
+        }
+    }
+
+    pub fn draw(&self, text: &str, style: &TextStyle) {
+        let scale = ui_scale();
+        let mut dims = self.measure(text, style);
+        let fitted;
+        let style = match style.max_width {
+            Some(max) if dims.x > max * scale => {
+                fitted = TextStyle { size: style.size * max * scale / dims.x, ..*style };
+                dims = self.measure(text, &fitted);
+                &fitted
+            }
+            _ => style,
+        };
+        let pos = style.anchor * vec2(screen_width(), screen_height()) + style.offset * scale;
+        let x = match style.align {
+            Align::Left => pos.x,
+            Align::Center => pos.x - dims.x / 2.0,
+            Align::Right => pos.x - dims.x,
+        };
+        let top = pos.y - dims.y / 2.0;
+
+        if let Some((shadow, color)) = style.shadow {
+            self.draw_run(text, style, x + shadow.x * scale, top + shadow.y * scale, color);
+        }
+        if let Some((thickness, color)) = style.outline {
+            let t = thickness * scale;
+            for (dx, dy) in [(-1.0, -1.0), (0.0, -1.0), (1.0, -1.0), (-1.0, 0.0), (1.0, 0.0), (-1.0, 1.0), (0.0, 1.0), (1.0, 1.0)] {
+                self.draw_run(text, style, x + dx * t, top + dy * t, color);
+            }
+        }
+        self.draw_run(text, style, x, top, style.color);
+    }
+
+    fn draw_run(&self, text: &str, style: &TextStyle, x: f32, top: f32, color: Color) {
+        let size = style.size * ui_scale();
+        match self.bitmap(text, style) {
+            Some(font) => font.draw(text, x, top, size, color),
+            None => {
+                let dims = measure_text(text, self.ui.as_ref(), size as u16, 1.0);
+                draw_text_ex(
+                    text,
+                    x,
+                    top + dims.offset_y,
+                    TextParams {
+                        font: self.ui.as_ref(),
+                        font_size: size as u16,
+                        color,
+                        ..Default::default()
+                    }
+                );
+            }
+        }
+    }
+}
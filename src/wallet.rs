@@ -0,0 +1,52 @@
+use std::fs;
+
+pub const WALLET_FILE: &str = "wallet.txt";
+
+// Coins collected over all runs, kept in wallet.txt next to the game as
+// `key = value` lines like settings.txt.
+pub struct Wallet {
+    pub coins: i64,
+}
+
+impl Wallet {
+    pub fn load() -> Wallet {
+        let mut wallet = Wallet { coins: 0 };
+        let Ok(text) = fs::read_to_string(WALLET_FILE) else {
+            return wallet;
+        };
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let ok = match line.split_once('=') {
+                Some((key, value)) => wallet.set(key.trim(), value.trim()).is_some(),
+                None => false,
+            };
+            if !ok {
+                println!("{}:{}: bad line: {}", WALLET_FILE, n + 1, line);
+            }
+        }
+        wallet
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = fs::write(WALLET_FILE, format!("coins = {}\n", self.coins)) {
+            println!("failed to save {}: {}", WALLET_FILE, err);
+        }
+    }
+
+    // Saving is up to the caller, so a run's coins don't rewrite the file
+    // one at a time.
+    pub fn add(&mut self, coins: i64) {
+        self.coins += coins;
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Option<()> {
+        match key {
+            "coins" => self.coins = value.parse::<i64>().ok()?.max(0),
+            _ => return None,
+        }
+        Some(())
+    }
+}
@@ -0,0 +1,119 @@
+use macroquad::prelude::*;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum WeatherKind {
+    Clear,
+    Rain,
+    Snow,
+    Fog,
+    Wind,
+}
+
+impl WeatherKind {
+    const ALL: [WeatherKind; 5] = [WeatherKind::Clear, WeatherKind::Rain, WeatherKind::Snow, WeatherKind::Fog, WeatherKind::Wind];
+
+    // "random" is accepted too and maps to None: a new kind is rolled each run.
+    pub fn parse(value: &str) -> Option<Option<WeatherKind>> {
+        match value {
+            "random" => Some(None),
+            _ => WeatherKind::ALL.iter().find(|kind| kind.name() == value).map(|kind| Some(*kind)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeatherKind::Clear => "clear",
+            WeatherKind::Rain => "rain",
+            WeatherKind::Snow => "snow",
+            WeatherKind::Fog => "fog",
+            WeatherKind::Wind => "wind",
+        }
+    }
+}
+
+const DROPS: usize = 150;
+
+// Weather drawn over the parallax. Wind is the only kind that affects play:
+// `wind()` is a per-frame force for the bird.
+pub struct Weather {
+    pub kind: WeatherKind,
+    drops: Vec<Vec2>,
+    time: f32,
+    // Direction of the current wind, picked per run: mostly horizontal
+    // gusts or mostly vertical updrafts/downdrafts.
+    wind_dir: Vec2,
+}
+
+impl Weather {
+    pub fn new(setting: Option<WeatherKind>) -> Weather {
+        let kind = setting.unwrap_or_else(|| WeatherKind::ALL[rand::gen_range(0, WeatherKind::ALL.len())]);
+        let wind_dir = match rand::gen_range(0, 4) {
+            0 => vec2(-1.0, 0.0),
+            1 => vec2(1.0, 0.0),
+            2 => vec2(0.0, -1.0),
+            _ => vec2(0.0, 1.0),
+        };
+        Weather {
+            kind,
+            drops: (0..DROPS)
+                .map(|_| vec2(rand::gen_range(0.0, screen_width()), rand::gen_range(0.0, screen_height())))
+                .collect(),
+            time: 0.0,
+            wind_dir,
+        }
+    }
+
+    pub fn wind(&self) -> Vec2 {
+        if self.kind != WeatherKind::Wind {
+            return Vec2::ZERO;
+        }
+        // Slow gusts that never quite reverse direction.
+        let strength = 0.12 + 0.1 * (self.time * 0.7).sin();
+        self.wind_dir * strength
+    }
+
+    pub fn update(&mut self, dt: f32, speed: f32) {
+        self.time += dt;
+        let fall = match self.kind {
+            WeatherKind::Rain => vec2(-speed * 60.0 * 0.5, 900.0),
+            WeatherKind::Snow => vec2(-speed * 60.0 * 0.3 + 30.0 * (self.time * 2.0).sin(), 80.0),
+            WeatherKind::Wind => vec2(-speed * 60.0 * 0.5, 0.0) + self.wind() * 4000.0,
+            _ => return,
+        };
+        let (w, h) = (screen_width(), screen_height());
+        for drop in &mut self.drops {
+            *drop += fall * dt;
+            drop.x = drop.x.rem_euclid(w);
+            drop.y = drop.y.rem_euclid(h);
+        }
+    }
+
+    pub fn draw(&self) {
+        match self.kind {
+            WeatherKind::Rain => {
+                for drop in &self.drops {
+                    draw_line(drop.x, drop.y, drop.x - 3.0, drop.y + 14.0, 1.5, Color::new(0.75, 0.8, 1.0, 0.5));
+                }
+            }
+            WeatherKind::Snow => {
+                for (n, drop) in self.drops.iter().enumerate() {
+                    draw_circle(drop.x, drop.y, 1.5 + (n % 3) as f32, Color::new(1.0, 1.0, 1.0, 0.8));
+                }
+            }
+            WeatherKind::Fog => {
+                for band in 0..3 {
+                    let y = screen_height() * (0.25 + 0.25 * band as f32) + 20.0 * (self.time * 0.3 + band as f32).sin();
+                    draw_rectangle(0.0, y - 60.0, screen_width(), 120.0, Color::new(0.9, 0.9, 0.95, 0.18));
+                }
+                draw_rectangle(0.0, 0.0, screen_width(), screen_height(), Color::new(0.85, 0.85, 0.9, 0.2));
+            }
+            WeatherKind::Wind => {
+                let streak = self.wind_dir * 40.0;
+                for drop in self.drops.iter().step_by(5) {
+                    draw_line(drop.x, drop.y, drop.x + streak.x, drop.y + streak.y, 1.0, Color::new(1.0, 1.0, 1.0, 0.25));
+                }
+            }
+            WeatherKind::Clear => {}
+        }
+    }
+}